@@ -0,0 +1,315 @@
+//! Short animations for projectiles, explosions and hits
+//!
+//! Animations are queued up and played one at a time. While any are pending, neither the player
+//! nor the monsters get to act, so everything resolves in the order it's seen on screen.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    combat::DamageEvent,
+    dungeon::{TilePos, TILE_SIZE_F32},
+    input_manager::{Action, Actions},
+    magic::CastSpellOn,
+    ui::Messages,
+    utils::SpriteLayer,
+    GameState,
+};
+
+/// Seconds a projectile takes to cross a single tile
+const BOLT_SECS_PER_TILE: f32 = 0.03;
+/// Seconds an explosion takes to expand by a single tile
+const BURST_SECS_PER_TILE: f32 = 0.06;
+/// Seconds a hit flash lasts
+const FLASH_SECS: f32 = 0.15;
+
+/// What an animation looks like
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationKind {
+    /// A projectile travelling tile by tile along a path
+    Bolt { path: Vec<TilePos>, color: Color },
+    /// An explosion expanding outward from its center to cover its tiles
+    Burst {
+        center: TilePos,
+        tiles: Vec<TilePos>,
+        color: Color,
+    },
+    /// A brief flash over an entity, e.g. when it's hit
+    Flash { entity: Entity, color: Color },
+}
+
+/// Something that happens once the animation showing it has finished playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationEffect {
+    /// A spell landing on its target
+    Spell(CastSpellOn),
+    /// A melee hit landing
+    Damage(DamageEvent),
+}
+
+impl From<CastSpellOn> for AnimationEffect {
+    fn from(cast: CastSpellOn) -> Self {
+        Self::Spell(cast)
+    }
+}
+
+impl From<DamageEvent> for AnimationEffect {
+    fn from(damage: DamageEvent) -> Self {
+        Self::Damage(damage)
+    }
+}
+
+/// An animation, and the effects that resolve once it has finished playing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub kind: AnimationKind,
+    pub then: Vec<AnimationEffect>,
+}
+
+impl Animation {
+    pub fn bolt(from: TilePos, to: TilePos, color: Color) -> Self {
+        Self::from(AnimationKind::Bolt {
+            path: from.line_to(to),
+            color,
+        })
+    }
+
+    pub fn burst(center: TilePos, tiles: Vec<TilePos>, color: Color) -> Self {
+        Self::from(AnimationKind::Burst {
+            center,
+            tiles,
+            color,
+        })
+    }
+
+    pub fn flash(entity: Entity, color: Color) -> Self {
+        Self::from(AnimationKind::Flash { entity, color })
+    }
+
+    /// Resolve the given effects, e.g. spells, once this animation has finished
+    pub fn then<E: Into<AnimationEffect>>(mut self, effects: impl IntoIterator<Item = E>) -> Self {
+        self.then.extend(effects.into_iter().map(Into::into));
+        self
+    }
+
+    /// How long this animation plays for, in seconds
+    fn duration(&self) -> f32 {
+        match &self.kind {
+            AnimationKind::Bolt { path, .. } => path.len() as f32 * BOLT_SECS_PER_TILE,
+            AnimationKind::Burst { center, tiles, .. } => {
+                let radius = tiles
+                    .iter()
+                    .map(|tile| center.distance(*tile))
+                    .max()
+                    .unwrap_or(0);
+                (radius + 1) as f32 * BURST_SECS_PER_TILE
+            }
+            AnimationKind::Flash { .. } => FLASH_SECS,
+        }
+    }
+}
+
+impl From<AnimationKind> for Animation {
+    fn from(kind: AnimationKind) -> Self {
+        Self {
+            kind,
+            then: Vec::new(),
+        }
+    }
+}
+
+/// An animation currently being played
+#[derive(Debug)]
+struct PlayingAnimation {
+    animation: Animation,
+    elapsed: f32,
+}
+
+/// Animations waiting to be played
+#[derive(Debug, Default, Resource)]
+pub struct AnimationQueue {
+    queue: VecDeque<Animation>,
+    playing: Option<PlayingAnimation>,
+}
+
+impl AnimationQueue {
+    pub fn push(&mut self, animation: Animation) {
+        self.queue.push_back(animation);
+    }
+
+    /// Whether there are no animations playing or waiting to be played
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty() && self.playing.is_none()
+    }
+}
+
+/// Player-configurable animation settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct AnimationSettings {
+    /// Whether to play animations at all; when disabled everything resolves instantly
+    pub enabled: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Marker component for the sprites spawned to show an animation
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+struct AnimationSprite;
+
+/// Run condition that is true when no animations are pending
+pub fn animations_finished(queue: Res<AnimationQueue>) -> bool {
+    queue.is_empty()
+}
+
+fn spawn_effect_sprite(commands: &mut Commands, at: Vec2, color: Color, size: f32) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(size)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(at.extend(SpriteLayer::Effect.as_f32())),
+            ..Default::default()
+        },
+        AnimationSprite,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_animations(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut queue: ResMut<AnimationQueue>,
+    sprites_qry: Query<Entity, With<AnimationSprite>>,
+    transform_qry: Query<&Transform>,
+    mut cast_spell_evt: EventWriter<CastSpellOn>,
+    mut damage_evt: EventWriter<DamageEvent>,
+) {
+    if queue.is_empty() {
+        return;
+    }
+
+    // Clear the previous frame of whatever's currently playing
+    for sprite in sprites_qry.iter() {
+        commands.entity(sprite).despawn();
+    }
+
+    loop {
+        let Some(mut playing) = queue.playing.take().or_else(|| {
+            queue.queue.pop_front().map(|animation| PlayingAnimation {
+                animation,
+                elapsed: 0.0,
+            })
+        }) else {
+            return;
+        };
+
+        playing.elapsed += time.delta_seconds();
+        let duration = playing.animation.duration();
+
+        if !settings.enabled || playing.elapsed >= duration {
+            // This one's done, let its effects go off and move on to the next
+            for effect in playing.animation.then {
+                match effect {
+                    AnimationEffect::Spell(cast) => cast_spell_evt.send(cast),
+                    AnimationEffect::Damage(damage) => damage_evt.send(damage),
+                }
+            }
+            if settings.enabled {
+                return;
+            }
+            continue;
+        }
+
+        let progress = playing.elapsed / duration;
+        match &playing.animation.kind {
+            AnimationKind::Bolt { path, color } => {
+                let idx = ((path.len() as f32 * progress) as usize).min(path.len() - 1);
+                spawn_effect_sprite(
+                    &mut commands,
+                    path[idx].as_vec(),
+                    *color,
+                    TILE_SIZE_F32 / 3.0,
+                );
+            }
+            AnimationKind::Burst {
+                center,
+                tiles,
+                color,
+            } => {
+                let radius = tiles
+                    .iter()
+                    .map(|tile| center.distance(*tile))
+                    .max()
+                    .unwrap_or(0);
+                let reached = ((radius + 1) as f32 * progress) as u32;
+                for &tile in tiles
+                    .iter()
+                    .filter(|tile| center.distance(**tile) <= reached)
+                {
+                    spawn_effect_sprite(&mut commands, tile.as_vec(), *color, TILE_SIZE_F32);
+                }
+            }
+            AnimationKind::Flash { entity, color } => {
+                // Follow the sprite, which may still be sliding into place
+                if let Ok(transform) = transform_qry.get(*entity) {
+                    spawn_effect_sprite(
+                        &mut commands,
+                        transform.translation.truncate(),
+                        *color,
+                        TILE_SIZE_F32,
+                    );
+                }
+            }
+        }
+
+        queue.playing = Some(playing);
+        return;
+    }
+}
+
+/// Drop any animations left over from a previous game
+fn clear_animations(
+    mut commands: Commands,
+    mut queue: ResMut<AnimationQueue>,
+    sprites_qry: Query<Entity, With<AnimationSprite>>,
+) {
+    *queue = AnimationQueue::default();
+    for sprite in sprites_qry.iter() {
+        commands.entity(sprite).despawn();
+    }
+}
+
+fn toggle_animations(
+    actions: Res<Actions>,
+    mut settings: ResMut<AnimationSettings>,
+    mut messages: ResMut<Messages>,
+) {
+    if actions.perform(Action::ToggleAnimations) {
+        settings.enabled = !settings.enabled;
+        if settings.enabled {
+            messages.add("Animations enabled");
+        } else {
+            messages.add("Animations disabled");
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AnimationQueue>()
+            .init_resource::<AnimationSettings>()
+            .add_systems(OnEnter(GameState::Setup), clear_animations)
+            .add_systems(Update, (play_animations, toggle_animations));
+    }
+}
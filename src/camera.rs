@@ -2,6 +2,7 @@ use bevy::prelude::*;
 
 use crate::{
     input_manager::{Action, Actions},
+    movement::SpriteMovement,
     setup::Player,
     GameState,
 };
@@ -15,9 +16,9 @@ fn setup_camera(mut commands: Commands, mut next_state: ResMut<NextState<GameSta
     next_state.set(GameState::MainMenu);
 }
 
-/// Update the camera's position when the player moves
+/// Update the camera's position when the player's sprite moves
 ///
-/// FIXME: This is really janky as there may be a 1-frame delay, resulting in flickering
+/// This runs after sprites have been moved for the frame so the camera never lags behind.
 #[allow(clippy::type_complexity)]
 fn camera_follow_player(
     mut camera_qry: Query<&mut Transform, With<PrimaryCamera>>,
@@ -59,7 +60,7 @@ impl Plugin for CameraPlugin {
             Update,
             (
                 setup_camera.run_if(in_state(GameState::Starting)),
-                camera_follow_player,
+                camera_follow_player.after(SpriteMovement),
                 camera_zoom,
             ),
         );
@@ -1,13 +1,17 @@
 use crate::{
+    animation::{Animation, AnimationQueue},
     bump::{handle_bumps, BumpEvent},
-    dungeon::TILE_SIZE_F32,
+    dungeon::{TilePos, TILE_SIZE_F32},
     fieldofview::HideOutOfSight,
+    movement::SpriteOffset,
     rand::prelude::*,
-    stats::{Attributes, SkillSheet},
+    setup::Player,
+    stats::{Attributes, Experience, SkillSheet},
     ui::Messages,
     utils::SpriteLayer,
+    GameState,
 };
-use bevy::prelude::*;
+use bevy::{ecs::query::Has, prelude::*};
 use std::{cmp::min, f32::consts::TAU};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
@@ -37,12 +41,32 @@ impl AttackEvent {
     }
 }
 
+/// A melee hit, dealt once the hit has been seen to land
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
 pub struct DamageEvent {
+    attacker: Entity,
     entity: Entity,
     damage: u16,
 }
 
+/// Who last hurt an entity, so that whoever lands the killing blow gets the credit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct LastHurtBy(pub Entity);
+
+/// Marks something that can't be killed, e.g. the player while testing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct GodMode;
+
+/// Sent when something dies
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub struct Died {
+    pub entity: Entity,
+    pub name: String,
+    /// Whoever landed the killing blow, if anyone
+    pub killer: Option<Entity>,
+    pub killer_name: Option<String>,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Component)]
 pub struct HP {
     current: u16,
@@ -77,24 +101,42 @@ impl HP {
         self.current = min(self.max, self.current.saturating_add(value));
     }
 
+    /// Restore every lost hit point
+    pub fn restore(&mut self) {
+        self.current = self.max;
+    }
+
     pub fn sub(&mut self, value: u16) {
         self.current = self.current.saturating_sub(value);
     }
 }
 
+/// How much damage an attacker can expect to do to a defender with each attack, taking misses
+/// and dodges into account
+pub fn expected_damage(
+    (attacker_skills, attacker_attributes): (&SkillSheet, &Attributes),
+    defender_skills: &SkillSheet,
+) -> f32 {
+    let hit_chance = attacker_skills.get("Attack").success_chance(0)
+        * (1.0 - defender_skills.get("Defense").success_chance(0));
+    let (min_damage, max_damage) = attacker_attributes.damage_range();
+
+    hit_chance * (min_damage + max_damage) as f32 / 2.0
+}
+
 fn attack(
     attacker_qry: Query<(&SkillSheet, &Attributes, Option<&Name>)>,
-    mut defender_qry: Query<(&mut HP, &SkillSheet, Option<&Name>)>,
+    defender_qry: Query<(&SkillSheet, Option<&Name>), With<HP>>,
     mut attack_events: EventReader<AttackEvent>,
-    mut damage_event: EventWriter<DamageEvent>,
     mut messages: ResMut<Messages>,
+    mut animations: ResMut<AnimationQueue>,
     rand: Res<Random>,
 ) {
     for event in attack_events.iter() {
         if let Ok((attacker_skills, attacker_attributes, attacker)) =
             attacker_qry.get(event.attacker)
         {
-            if let Ok((mut hp, defender_skills, defender)) = defender_qry.get_mut(event.target) {
+            if let Ok((defender_skills, defender)) = defender_qry.get(event.target) {
                 let mut rng = rand.from_entropy();
 
                 let attack = attacker_skills.get("Attack");
@@ -146,36 +188,54 @@ fn attack(
                             messages.add_notice(message);
                         }
                     }
-                    hp.sub(damage);
-                    damage_event.send(DamageEvent {
-                        entity: event.target,
-                        damage,
-                    });
+                    animations.push(
+                        Animation::flash(event.target, Color::RED.with_a(0.5)).then([
+                            DamageEvent {
+                                attacker: event.attacker,
+                                entity: event.target,
+                                damage,
+                            },
+                        ]),
+                    );
                 }
             }
         }
     }
 }
 
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_event: EventReader<DamageEvent>,
+    mut hp_qry: Query<&mut HP>,
+) {
+    for event in damage_event.iter() {
+        if let Ok(mut hp) = hp_qry.get_mut(event.entity) {
+            hp.sub(event.damage);
+            commands
+                .entity(event.entity)
+                .insert(LastHurtBy(event.attacker));
+        }
+    }
+}
+
 fn splatter_blood(
     mut damage_event: EventReader<DamageEvent>,
-    transform_qry: Query<&Transform>,
+    pos_qry: Query<&TilePos>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     rand: Res<Random>,
 ) {
     let mut rng = rand.from_entropy();
     for event in damage_event.iter() {
-        if let Ok(transform) = transform_qry.get(event.entity) {
+        if let Ok(pos) = pos_qry.get(event.entity) {
             let scale = rng.gen_range(0.3..1.0);
             let rot = rng.gen_range(0.0..TAU);
             let displace = rng.gen_range(0.0..(TILE_SIZE_F32 / 2.0));
             let displace_rot = rng.gen_range(0.0..TAU);
             let blood = format!("sprites/blood/blood_red{:02}.png", rng.gen_range(0..30));
 
-            let mut transform = *transform;
-            transform.translation += Vec2::from_angle(displace_rot).extend(0.0) * displace;
-            transform.translation.z = SpriteLayer::Decoration.as_f32();
+            let offset = Vec2::from_angle(displace_rot) * displace;
+            let mut transform = pos.as_transform(SpriteLayer::Decoration);
             transform.rotate_z(rot);
             transform.scale = Vec3::splat(scale);
 
@@ -185,27 +245,76 @@ fn splatter_blood(
                     transform,
                     ..Default::default()
                 },
+                *pos,
+                SpriteOffset(offset),
                 HideOutOfSight::Darken,
             ));
         }
     }
 }
 
-fn remove_dead(
-    dead_qry: Query<(Entity, &HP, Option<&Name>), Changed<HP>>,
+/// Heal anything in god mode right back up, before anyone can notice it died
+fn restore_gods(mut god_qry: Query<&mut HP, (With<GodMode>, Changed<HP>)>) {
+    for mut hp in god_qry.iter_mut() {
+        hp.restore();
+    }
+}
+
+/// Reward whoever landed a killing blow with experience, the tougher the victim the more
+fn award_experience(
+    dead_qry: Query<(&HP, &LastHurtBy), Changed<HP>>,
+    mut experience_qry: Query<&mut Experience>,
+    mut messages: ResMut<Messages>,
+) {
+    for (hp, &LastHurtBy(killer)) in dead_qry.iter() {
+        if hp.current() > 0 {
+            continue;
+        }
+
+        if let Ok(mut experience) = experience_qry.get_mut(killer) {
+            if experience.gain(u32::from(hp.max())) {
+                messages.add_friendly(format!("You reach level {}!", experience.level()));
+            }
+        }
+    }
+}
+
+/// Clear away the dead, or end the game if it's the player who died
+///
+/// This runs after everything that can hurt something has had its turn, so the killing blow is
+/// always known.
+#[allow(clippy::type_complexity)]
+pub fn remove_dead(
+    dead_qry: Query<(Entity, &HP, Option<&Name>, Option<&LastHurtBy>, Has<Player>), Changed<HP>>,
+    names_qry: Query<&Name>,
     mut commands: Commands,
     mut messages: ResMut<Messages>,
+    mut died_evt: EventWriter<Died>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
-    for (entity, hp, name) in dead_qry.iter() {
+    for (entity, hp, name, last_hurt_by, is_player) in dead_qry.iter() {
         if hp.current() == 0 {
+            let killer = last_hurt_by.map(|&LastHurtBy(killer)| killer);
+            died_evt.send(Died {
+                entity,
+                name: name.map(|name| name.to_string()).unwrap_or_default(),
+                killer,
+                killer_name: killer
+                    .and_then(|killer| names_qry.get(killer).ok())
+                    .map(|name| name.to_string()),
+            });
+
+            if is_player {
+                // Leave the player be, there's still a run to sum up
+                messages.add_hostile("YOU DIED!");
+                next_state.set(GameState::GameOver);
+                continue;
+            }
+
             if let Some(name) = name {
-                if name.as_str() == "Player" {
-                    messages.add_hostile("YOU DIED!");
-                } else {
-                    messages.add_notice(format!("{name} is dead!"));
-                }
+                messages.add_notice(format!("{name} is dead!"));
             }
-            commands.entity(entity).despawn();
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
@@ -217,12 +326,14 @@ impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<AttackEvent>()
             .add_event::<DamageEvent>()
+            .add_event::<Died>()
             .add_systems(
                 Update,
-                (
-                    (attack, remove_dead).chain().after(handle_bumps),
-                    splatter_blood,
-                ),
+                (attack.after(handle_bumps), apply_damage, splatter_blood),
+            )
+            .add_systems(
+                PostUpdate,
+                (restore_gods, award_experience, remove_dead).chain(),
             );
     }
 }
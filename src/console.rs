@@ -0,0 +1,308 @@
+//! Wizard commands for testing, typed into the console
+//!
+//! These skip the usual rules of the game entirely, e.g. to try out a new monster without having
+//! to go looking for one.
+
+use std::str::FromStr;
+
+use bevy::{
+    ecs::{query::Has, system::SystemParam},
+    prelude::*,
+};
+
+use crate::{
+    combat::{GodMode, HP},
+    dungeon::{reveal_tiles, BlocksMovement, Map, Tile, TilePos},
+    fieldofview::FieldOfView,
+    inventory::Inventory,
+    items::ItemList,
+    mobs::MobList,
+    movement::snap_to_tile,
+    record::RunRecord,
+    setup::Player,
+    ui::Messages,
+    utils::SpriteLayer,
+    GameState,
+};
+
+/// Every command the console knows, for completing what's typed
+pub const COMMANDS: [&str; 8] = [
+    "spawn", "give", "tp", "heal", "godmode", "reveal", "descend", "seed",
+];
+
+/// A line typed into the console, to be run as a command
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub struct ConsoleCommand(pub String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WizardCommand {
+    /// Spawn a monster next to the player
+    Spawn(String),
+    /// Put an item in the player's inventory
+    Give(String),
+    /// Move the player straight to a tile
+    Teleport(TilePos),
+    /// Restore all of the player's hit points
+    Heal,
+    /// Toggle whether the player can be killed
+    GodMode,
+    /// Reveal the entire map
+    Reveal,
+    /// Go down to the next level of the dungeon
+    Descend,
+    /// Show the seed the run was generated from
+    Seed,
+}
+
+impl FromStr for WizardCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (command, args) = line
+            .trim()
+            .split_once(' ')
+            .map_or((line.trim(), ""), |(command, args)| (command, args.trim()));
+        let no_args = |wizard_command: WizardCommand| {
+            if args.is_empty() {
+                Ok(wizard_command)
+            } else {
+                Err(format!("{command} doesn't take any arguments"))
+            }
+        };
+
+        match command.to_lowercase().as_str() {
+            "spawn" if args.is_empty() => Err("Usage: spawn <mob>".to_string()),
+            "spawn" => Ok(WizardCommand::Spawn(args.to_string())),
+            "give" if args.is_empty() => Err("Usage: give <item>".to_string()),
+            "give" => Ok(WizardCommand::Give(args.to_string())),
+            "tp" => match args
+                .split_whitespace()
+                .map(u32::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .as_deref()
+            {
+                Ok(&[x, y]) => Ok(WizardCommand::Teleport(TilePos::new(x, y))),
+                _ => Err("Usage: tp <x> <y>".to_string()),
+            },
+            "heal" => no_args(WizardCommand::Heal),
+            "godmode" => no_args(WizardCommand::GodMode),
+            "reveal" => no_args(WizardCommand::Reveal),
+            "descend" => no_args(WizardCommand::Descend),
+            "seed" => no_args(WizardCommand::Seed),
+            _ => Err(format!("Unknown command \"{command}\"")),
+        }
+    }
+}
+
+/// Everything the wizard commands need to get at
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+struct Wizard<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    mob_list: Res<'w, MobList>,
+    item_list: Res<'w, ItemList>,
+    map: Res<'w, Map>,
+    record: Res<'w, RunRecord>,
+    player_qry: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut TilePos,
+            &'static mut Transform,
+            &'static mut HP,
+            &'static mut Inventory,
+            Has<GodMode>,
+        ),
+        With<Player>,
+    >,
+    blockers_qry: Query<'w, 's, &'static TilePos, (With<BlocksMovement>, Without<Player>)>,
+    tile_qry: Query<'w, 's, &'static mut FieldOfView, With<Tile>>,
+    next_state: ResMut<'w, NextState<GameState>>,
+}
+
+impl<'w, 's> Wizard<'w, 's> {
+    /// Run a command, describing what happened
+    fn run(&mut self, command: WizardCommand) -> Result<String, String> {
+        let (player, mut player_pos, mut player_transform, mut hp, mut inventory, has_god_mode) =
+            self.player_qry
+                .get_single_mut()
+                .map_err(|_| "There's no player".to_string())?;
+        let blockers_qry = &self.blockers_qry;
+        let is_blocked = |tile| blockers_qry.iter().any(|&blocker| blocker == tile);
+
+        match command {
+            WizardCommand::Spawn(name) => {
+                let mob_data = self.mob_list.get(name).map_err(|err| err.to_string())?;
+                let tile = self
+                    .map
+                    .neighbors_of(*player_pos)
+                    .into_iter()
+                    .find(|&tile| !is_blocked(tile))
+                    .ok_or("There's no room next to the player")?;
+
+                let mob = mob_data.spawn(&mut self.commands, &self.asset_server);
+                self.commands
+                    .entity(mob)
+                    .insert((tile, tile.as_transform(SpriteLayer::Actor)));
+
+                Ok(format!("Spawned {}", mob_data.name()))
+            }
+            WizardCommand::Give(name) => {
+                let item = self.item_list.get(name).map_err(|err| err.to_string())?;
+
+                if inventory.insert(item, &self.item_list) {
+                    Ok(format!("Given {}", self.item_list[item].name))
+                } else {
+                    Err("The player's inventory is full".to_string())
+                }
+            }
+            WizardCommand::Teleport(tile) => {
+                if !self.map.size.in_bounds(tile) {
+                    Err(format!("({}, {}) is off the map", tile.x, tile.y))
+                } else if is_blocked(tile) {
+                    Err(format!("({}, {}) is blocked", tile.x, tile.y))
+                } else {
+                    *player_pos = tile;
+                    snap_to_tile(&mut self.commands, player, tile, &mut player_transform);
+                    Ok(format!("Teleported to ({}, {})", tile.x, tile.y))
+                }
+            }
+            WizardCommand::Heal => {
+                hp.restore();
+                Ok("Healed".to_string())
+            }
+            WizardCommand::GodMode if has_god_mode => {
+                self.commands.entity(player).remove::<GodMode>();
+                Ok("God mode disabled".to_string())
+            }
+            WizardCommand::GodMode => {
+                self.commands.entity(player).insert(GodMode);
+                Ok("God mode enabled".to_string())
+            }
+            WizardCommand::Reveal => {
+                reveal_tiles(self.tile_qry.iter_mut());
+                Ok("Revealed the map".to_string())
+            }
+            WizardCommand::Descend => {
+                self.next_state.set(GameState::Descending);
+                Ok(format!("Leaving depth {}", self.record.depth))
+            }
+            WizardCommand::Seed => Ok(format!("Seed: {}", self.record.seed)),
+        }
+    }
+}
+
+fn run_console_commands(
+    mut command_evt: EventReader<ConsoleCommand>,
+    mut wizard: Wizard,
+    mut messages: ResMut<Messages>,
+) {
+    for ConsoleCommand(line) in command_evt.iter() {
+        messages.add(format!("> {line}"));
+
+        match line.parse().and_then(|command| wizard.run(command)) {
+            Ok(result) => messages.add_notice(result),
+            Err(err) => messages.add_hostile(err),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ConsoleCommand>().add_systems(
+            Update,
+            run_console_commands.run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<WizardCommand, String> {
+        line.parse()
+    }
+
+    #[test]
+    fn commands_without_arguments() {
+        assert_eq!(parse("heal"), Ok(WizardCommand::Heal));
+        assert_eq!(parse("godmode"), Ok(WizardCommand::GodMode));
+        assert_eq!(parse("reveal"), Ok(WizardCommand::Reveal));
+        assert_eq!(parse("descend"), Ok(WizardCommand::Descend));
+        assert_eq!(parse("seed"), Ok(WizardCommand::Seed));
+    }
+
+    #[test]
+    fn commands_ignore_case_and_surrounding_space() {
+        assert_eq!(parse("  HEAL "), Ok(WizardCommand::Heal));
+        assert_eq!(
+            parse("Tp 3 4"),
+            Ok(WizardCommand::Teleport(TilePos::new(3, 4)))
+        );
+    }
+
+    #[test]
+    fn extra_arguments() {
+        assert_eq!(
+            parse("heal everyone"),
+            Err("heal doesn't take any arguments".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_command() {
+        assert_eq!(
+            parse("fly away"),
+            Err("Unknown command \"fly\"".to_string())
+        );
+        assert_eq!(parse(""), Err("Unknown command \"\"".to_string()));
+    }
+
+    #[test]
+    fn teleport() {
+        assert_eq!(
+            parse("tp 10 20"),
+            Ok(WizardCommand::Teleport(TilePos::new(10, 20)))
+        );
+        assert_eq!(
+            parse("tp  10   20 "),
+            Ok(WizardCommand::Teleport(TilePos::new(10, 20)))
+        );
+    }
+
+    #[test]
+    fn teleport_with_bad_coordinates() {
+        let usage = Err("Usage: tp <x> <y>".to_string());
+
+        assert_eq!(parse("tp"), usage);
+        assert_eq!(parse("tp 10"), usage);
+        assert_eq!(parse("tp 10 20 30"), usage);
+        assert_eq!(parse("tp x y"), usage);
+        assert_eq!(parse("tp -1 5"), usage);
+        assert_eq!(parse("tp 1.5 5"), usage);
+    }
+
+    #[test]
+    fn spawn_and_give_take_whole_names() {
+        assert_eq!(
+            parse("spawn Orc Captain"),
+            Ok(WizardCommand::Spawn("Orc Captain".to_string()))
+        );
+        assert_eq!(
+            parse("give  Scroll of Fireball "),
+            Ok(WizardCommand::Give("Scroll of Fireball".to_string()))
+        );
+    }
+
+    #[test]
+    fn spawn_and_give_need_a_name() {
+        assert_eq!(parse("spawn"), Err("Usage: spawn <mob>".to_string()));
+        assert_eq!(parse("give   "), Err("Usage: give <item>".to_string()));
+    }
+}
@@ -0,0 +1,190 @@
+//! A debug overlay, toggled with [`Action::ToggleDebug`]
+//!
+//! Shows a few numbers about the game's state in the corner of the screen, and draws the dungeon's
+//! room layout and where monsters are heading over the map.
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    dungeon::{Map, Tile, TilePos, TILE_SIZE_F32},
+    input_manager::{Action, Actions},
+    items::ItemId,
+    mobs::{AiTarget, Mob},
+    record::RunRecord,
+    setup::Player,
+    ui::{GameUi, Messages},
+    GameState, TurnState,
+};
+
+/// Whether the debug overlay is shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct DebugSettings {
+    pub enabled: bool,
+}
+
+/// Marker component for the debug overlay's text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+struct DebugOverlay;
+
+fn toggle_debug(
+    actions: Res<Actions>,
+    mut settings: ResMut<DebugSettings>,
+    mut messages: ResMut<Messages>,
+) {
+    if actions.perform(Action::ToggleDebug) {
+        settings.enabled = !settings.enabled;
+        if settings.enabled {
+            messages.add("Debug overlay enabled");
+        } else {
+            messages.add("Debug overlay disabled");
+        }
+    }
+}
+
+fn spawn_debug_overlay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<DebugSettings>,
+    overlay_qry: Query<Entity, With<DebugOverlay>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for overlay in overlay_qry.iter() {
+        commands.entity(overlay).despawn_recursive();
+    }
+
+    if settings.enabled {
+        commands.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 20.0,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..Default::default()
+            })
+            .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+            // Shown over the top of everything else
+            ZIndex::Global(2),
+            DebugOverlay,
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn update_debug_overlay(
+    mut overlay_qry: Query<&mut Text, With<DebugOverlay>>,
+    diagnostics: Res<DiagnosticsStore>,
+    record: Res<RunRecord>,
+    (game_state, turn_state, ui_state): (
+        Res<State<GameState>>,
+        Res<State<TurnState>>,
+        Res<State<GameUi>>,
+    ),
+    player_qry: Query<&TilePos, With<Player>>,
+    entity_qry: Query<()>,
+    mob_qry: Query<(), With<Mob>>,
+    item_qry: Query<(), With<ItemId>>,
+    tile_qry: Query<(), With<Tile>>,
+) {
+    let Ok(mut text) = overlay_qry.get_single_mut() else {
+        return;
+    };
+
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let player_pos = player_qry
+        .get_single()
+        .map_or_else(|_| "-".to_string(), |pos| format!("({}, {})", pos.x, pos.y));
+
+    text.sections[0].value = [
+        format!("FPS: {fps:.0}"),
+        format!("Seed: {}", record.seed),
+        format!("Turn: {}", record.turns),
+        format!("Player: {player_pos}"),
+        format!(
+            "Entities: {} ({} mobs, {} items, {} tiles)",
+            entity_qry.iter().count(),
+            mob_qry.iter().count(),
+            item_qry.iter().count(),
+            tile_qry.iter().count(),
+        ),
+        format!(
+            "State: {:?} / {:?} / {:?}",
+            **game_state, **turn_state, **ui_state
+        ),
+    ]
+    .join("\n");
+}
+
+/// Draw the rooms and the corridors connecting them, plus where each monster is heading
+fn draw_debug_gizmos(
+    mut gizmos: Gizmos,
+    map: Res<Map>,
+    ai_qry: Query<(&TilePos, &AiTarget), With<Mob>>,
+) {
+    for room in map.rooms.rooms() {
+        let (start, end) = (room.start().as_vec(), room.end().as_vec());
+        gizmos.rect_2d(
+            (start + end) / 2.0,
+            0.0,
+            end - start + Vec2::splat(TILE_SIZE_F32),
+            Color::CYAN,
+        );
+    }
+
+    for (room, other) in map.rooms.edges() {
+        gizmos.line_2d(
+            room.center().as_vec(),
+            other.center().as_vec(),
+            Color::YELLOW,
+        );
+    }
+
+    for (pos, ai_target) in ai_qry.iter() {
+        gizmos.linestrip_2d(
+            std::iter::once(pos)
+                .chain(&ai_target.path)
+                .map(|tile| tile.as_vec()),
+            Color::RED,
+        );
+        gizmos.circle_2d(ai_target.target.as_vec(), TILE_SIZE_F32 / 4.0, Color::RED);
+    }
+}
+
+fn debug_enabled(settings: Res<DebugSettings>) -> bool {
+    settings.enabled
+}
+
+#[derive(Debug, Default)]
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FrameTimeDiagnosticsPlugin)
+            .init_resource::<DebugSettings>()
+            .add_systems(
+                Update,
+                (
+                    (toggle_debug, spawn_debug_overlay).chain(),
+                    update_debug_overlay
+                        .after(spawn_debug_overlay)
+                        .run_if(debug_enabled),
+                    draw_debug_gizmos.run_if(debug_enabled.and_then(resource_exists::<Map>())),
+                ),
+            );
+    }
+}
@@ -2,7 +2,7 @@ use crate::rand::prelude::*;
 use bevy::prelude::*;
 
 mod map;
-pub use map::{Map, MapSize};
+pub use map::{reveal_tiles, Map, MapSize};
 mod room;
 pub use room::{RectangularRoom, RoomGraph};
 mod tiles;
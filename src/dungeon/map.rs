@@ -6,6 +6,18 @@ use crate::{
     input_manager::{Action, Actions},
 };
 
+/// Offsets to each of a tile's eight neighbors, orthogonal neighbors first
+const NEIGHBOR_OFFSETS: [IVec2; 8] = [
+    IVec2::new(0, 1),
+    IVec2::new(1, 0),
+    IVec2::new(0, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, -1),
+    IVec2::new(-1, 1),
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MapSize {
     pub width: u32,
@@ -35,7 +47,7 @@ impl MapSize {
     }
 
     pub fn center_tile(&self) -> TilePos {
-        TilePos::from(self.center())
+        TilePos::new(self.width / 2, self.height / 2)
     }
 
     pub fn in_bounds(&self, pos: TilePos) -> bool {
@@ -74,6 +86,7 @@ impl Map {
                     .spawn((
                         TileBundle::wall(),
                         Tile::sprite_bundle(pos, asset_server.load("sprites/catacombs2.png")),
+                        pos,
                     ))
                     .id()
             })
@@ -96,50 +109,11 @@ impl Map {
     }
 
     pub fn neighbors_of(&self, pos: TilePos) -> Vec<TilePos> {
-        let mut neighbors = Vec::with_capacity(8);
-
-        let north = pos + TilePos::new(0, 1);
-        if self.size.in_bounds(north) {
-            neighbors.push(north);
-        }
-        let east = pos + TilePos::new(1, 0);
-        if self.size.in_bounds(east) {
-            neighbors.push(east);
-        }
-        // South
-        if pos.y > 0 {
-            neighbors.push(pos - TilePos::new(0, 1));
-        }
-        // West
-        if pos.x > 0 {
-            neighbors.push(pos - TilePos::new(1, 0));
-        }
-
-        let northeast = pos + TilePos::new(1, 1);
-        if self.size.in_bounds(northeast) {
-            neighbors.push(northeast);
-        }
-
-        // Southeast
-        if pos.y > 0 {
-            let southeast = pos - TilePos::new(0, 1) + TilePos::new(1, 0);
-            if self.size.in_bounds(southeast) {
-                neighbors.push(southeast);
-            }
-        }
-        // Southwest
-        if pos.y > 0 && pos.x > 0 {
-            neighbors.push(pos - TilePos::new(1, 1));
-        }
-        // Northwest
-        if pos.x > 0 {
-            let northwest = pos - TilePos::new(1, 0) + TilePos::new(0, 1);
-            if self.size.in_bounds(northwest) {
-                neighbors.push(northwest);
-            }
-        }
-
-        neighbors
+        NEIGHBOR_OFFSETS
+            .iter()
+            .filter_map(|&offset| pos.checked_add_signed(offset))
+            .filter(|&neighbor| self.size.in_bounds(neighbor))
+            .collect()
     }
 
     pub fn iter_rooms(&self) -> impl Iterator<Item = &RectangularRoom> {
@@ -183,12 +157,17 @@ impl Map {
     }
 }
 
+/// Mark every unexplored tile as explored, as though the player had already seen them all
+pub fn reveal_tiles<'a>(tiles: impl IntoIterator<Item = Mut<'a, FieldOfView>>) {
+    for mut fov in tiles {
+        if *fov == FieldOfView::Unexplored {
+            *fov = FieldOfView::NotVisible;
+        }
+    }
+}
+
 pub fn reveal_map(actions: Res<Actions>, mut tiles: Query<&mut FieldOfView, With<Tile>>) {
     if actions.perform(Action::RevealMap) {
-        for mut fov in tiles.iter_mut() {
-            if *fov == FieldOfView::Unexplored {
-                *fov = FieldOfView::NotVisible;
-            }
-        }
+        reveal_tiles(tiles.iter_mut());
     }
 }
@@ -99,6 +99,16 @@ impl RectangularRoom {
         }
     }
 
+    /// The corner the room starts from, at its lowest x and y
+    pub fn start(&self) -> TilePos {
+        self.start
+    }
+
+    /// The opposite corner to [`Self::start`], inclusive
+    pub fn end(&self) -> TilePos {
+        self.end
+    }
+
     pub fn center(&self) -> TilePos {
         (self.start + self.end) / 2
     }
@@ -20,6 +20,69 @@ impl TilePos {
         max(self.x.abs_diff(other.x), self.y.abs_diff(other.y))
     }
 
+    /// The tile containing a point in world coordinates, if there is one
+    pub fn from_world(point: Vec2) -> Option<Self> {
+        let tile = (point / TILE_SIZE_F32).round();
+        if tile.x < 0.0 || tile.y < 0.0 {
+            None
+        } else {
+            Some(Self::new(tile.x as u32, tile.y as u32))
+        }
+    }
+
+    /// This position as a signed vector
+    pub fn as_ivec(&self) -> IVec2 {
+        IVec2::new(self.x as i32, self.y as i32)
+    }
+
+    /// Offset this position, returning `None` if that would leave the positive quadrant
+    pub fn checked_add_signed(&self, offset: IVec2) -> Option<Self> {
+        Some(Self {
+            x: self.x.checked_add_signed(offset.x)?,
+            y: self.y.checked_add_signed(offset.y)?,
+        })
+    }
+
+    /// Offset this position, stopping at zero rather than leaving the positive quadrant
+    pub fn saturating_add_signed(&self, offset: IVec2) -> Self {
+        Self {
+            x: self.x.saturating_add_signed(offset.x),
+            y: self.y.saturating_add_signed(offset.y),
+        }
+    }
+
+    /// Tiles along a straight line from this position to another, including both ends
+    pub fn line_to(&self, other: TilePos) -> Vec<TilePos> {
+        // Bresenham's line algorithm, generalized to all octants
+        let (mut x, mut y) = (self.x as i64, self.y as i64);
+        let (x1, y1) = (other.x as i64, other.y as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        let mut line = Vec::with_capacity(self.distance(other) as usize + 1);
+        loop {
+            line.push(TilePos::new(x as u32, y as u32));
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+
+        line
+    }
+
     /// Corner of the tile in world coordinates, useful to e.g. place UI elements over the tile
     pub fn corner(&self) -> Vec2 {
         self.as_vec() + Vec2::new(-TILE_SIZE_F32 / 2.0, TILE_SIZE_F32 / 2.0)
@@ -48,45 +111,6 @@ impl TilePos {
     }
 }
 
-impl From<Transform> for TilePos {
-    fn from(value: Transform) -> Self {
-        Self::from(value.translation)
-    }
-}
-
-impl From<&Transform> for TilePos {
-    fn from(value: &Transform) -> Self {
-        Self::from(*value)
-    }
-}
-
-impl From<Vec3> for TilePos {
-    fn from(value: Vec3) -> Self {
-        Self::from(value.truncate())
-    }
-}
-
-impl From<&Vec3> for TilePos {
-    fn from(value: &Vec3) -> Self {
-        Self::from(*value)
-    }
-}
-
-impl From<Vec2> for TilePos {
-    fn from(value: Vec2) -> Self {
-        Self {
-            x: (value.x / TILE_SIZE_F32).round() as u32,
-            y: (value.y / TILE_SIZE_F32).round() as u32,
-        }
-    }
-}
-
-impl From<&Vec2> for TilePos {
-    fn from(value: &Vec2) -> Self {
-        Self::from(*value)
-    }
-}
-
 impl From<(u32, u32)> for TilePos {
     fn from((x, y): (u32, u32)) -> Self {
         Self { x, y }
@@ -116,20 +140,12 @@ impl std::ops::AddAssign for TilePos {
     }
 }
 
+/// The difference between two positions is a signed offset, which can never underflow
 impl std::ops::Sub for TilePos {
-    type Output = Self;
+    type Output = IVec2;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-        }
-    }
-}
-
-impl std::ops::SubAssign for TilePos {
-    fn sub_assign(&mut self, rhs: Self) {
-        *self = *self - rhs;
+        self.as_ivec() - rhs.as_ivec()
     }
 }
 
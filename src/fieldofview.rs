@@ -28,26 +28,20 @@ pub enum HideOutOfSight {
 
 #[allow(clippy::type_complexity)]
 pub fn update_fov(
-    player_qry: Query<&Transform, (With<Player>, Changed<Transform>)>,
-    blocks_sight_qry: Query<&Transform, With<BlocksSight>>,
+    player_qry: Query<&TilePos, (With<Player>, Changed<TilePos>)>,
+    blocks_sight_qry: Query<&TilePos, With<BlocksSight>>,
     mut fov_set: ParamSet<(
-        Query<(&mut FieldOfView, &mut Sprite, &mut Visibility, &Transform), With<Tile>>,
-        Query<(&mut Visibility, &mut Sprite, &Transform, &HideOutOfSight)>,
+        Query<(&mut FieldOfView, &mut Sprite, &mut Visibility, &TilePos), With<Tile>>,
+        Query<(&mut Visibility, &mut Sprite, &TilePos, &HideOutOfSight)>,
     )>,
 ) {
-    if let Ok(player_transform) = player_qry.get_single() {
-        let player_pos = TilePos::from(*player_transform);
-
-        let blockers: HashSet<_> = blocks_sight_qry
-            .iter()
-            .map(|transform| TilePos::from(*transform))
-            .collect();
+    if let Ok(&player_pos) = player_qry.get_single() {
+        let blockers: HashSet<_> = blocks_sight_qry.iter().copied().collect();
 
         let fov = shadowcasting::compute_fov(player_pos, |tile| blockers.contains(&tile));
 
-        for (mut tile_fov, mut sprite, mut visibility, transform) in fov_set.p0().iter_mut() {
-            let pos = TilePos::from(transform);
-            if fov.contains(&pos) {
+        for (mut tile_fov, mut sprite, mut visibility, pos) in fov_set.p0().iter_mut() {
+            if fov.contains(pos) {
                 *tile_fov = FieldOfView::Visible;
                 *visibility = Visibility::Visible;
                 sprite.color = Color::default();
@@ -57,8 +51,8 @@ pub fn update_fov(
             }
         }
 
-        for (mut visibility, mut sprite, transform, &hide) in fov_set.p1().iter_mut() {
-            if fov.contains(&TilePos::from(transform)) {
+        for (mut visibility, mut sprite, pos, &hide) in fov_set.p1().iter_mut() {
+            if fov.contains(pos) {
                 *visibility = Visibility::Visible;
                 sprite.color = Color::default();
             } else {
@@ -5,25 +5,34 @@
 use bevy::prelude::*;
 
 mod keyboard;
-pub use keyboard::{Action, ActionModifier, Actions};
+pub use keyboard::{Action, ActionModifier, Actions, BoundKey, KeyMap, KeyMapError};
 
-use crate::TurnState;
+use crate::{animation::AnimationQueue, TurnState};
 
 /// Label for the input manager systems to facilitate relative ordering
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, SystemSet)]
 pub struct InputManager;
 
+/// Whether the game is waiting on the player, with nothing left to play out from the last turn
+fn player_can_act(turn_state: Res<State<TurnState>>, animations: Res<AnimationQueue>) -> bool {
+    *turn_state == TurnState::WaitingForPlayer && animations.is_empty()
+}
+
 /// Plugin to add the input handling system into the game
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct InputManagerPlugin;
 
 impl Plugin for InputManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Actions>().add_systems(
-            Update,
-            keyboard::update_actions
-                .in_set(InputManager)
-                .run_if(in_state(TurnState::WaitingForPlayer)),
-        );
+        app.init_resource::<Actions>()
+            .add_systems(Startup, keyboard::load_keymap)
+            .add_systems(
+                Update,
+                (
+                    keyboard::update_actions.run_if(player_can_act),
+                    keyboard::update_global_actions.run_if(not(player_can_act)),
+                )
+                    .in_set(InputManager),
+            );
     }
 }
@@ -7,11 +7,12 @@ use std::{collections::HashMap, ops::Index};
 use bevy::prelude::*;
 
 mod keymap;
-use keymap::KeyMap;
+pub use keymap::{BoundKey, KeyMap, KeyMapError};
 
-use crate::TurnState;
-
-use self::keymap::BoundKey;
+use crate::{
+    ui::{GameUi, Messages},
+    GameState, TurnState,
+};
 
 /// A game action that can be bound to a key
 #[non_exhaustive]
@@ -19,10 +20,42 @@ use self::keymap::BoundKey;
 pub enum Action {
     /// Open the inventory screen
     OpenInventory,
+    /// Open the key bindings screen
+    OpenKeybindings,
+    /// Open the full message history
+    MessageHistory,
+    /// Open the character sheet
+    CharacterSheet,
+    /// Examine the surroundings with the targeting cursor
+    Look,
+    /// Travel to a tile picked with the targeting cursor
+    Travel,
+    /// Explore the level until something interesting happens
+    AutoExplore,
+    /// Pick up what's lying underfoot
+    PickUp,
+    /// Drop the inventory item under the mouse
+    Drop,
+    /// Throw the inventory item under the mouse at a tile picked with the targeting cursor
+    Throw,
+    /// Toggle picking up the inventory item under the mouse automatically
+    ToggleAutopickup,
+    /// Confirm the current selection, e.g. the target under the cursor
+    Confirm,
+    /// Cancel the current selection and return to the game
+    Cancel,
+    /// Remove the selected key binding on the key bindings screen
+    RemoveBinding,
+    /// Cycle the targeting cursor through visible hostiles
+    CycleTarget,
     /// Reveal the entire map
     RevealMap,
     /// Toggle showing the debug menu
     ToggleDebug,
+    /// Open or close the wizard console
+    ToggleConsole,
+    /// Toggle playing animations, e.g. of spells and attacks
+    ToggleAnimations,
     /// Spend a turn doing nothing at all
     Wait,
     /// Walk north
@@ -41,6 +74,22 @@ pub enum Action {
     WalkSouthwest,
     /// Walk northwest
     WalkNorthwest,
+    /// Run north
+    RunNorth,
+    /// Run east
+    RunEast,
+    /// Run south
+    RunSouth,
+    /// Run west
+    RunWest,
+    /// Run northeast
+    RunNortheast,
+    /// Run southeast
+    RunSoutheast,
+    /// Run southwest
+    RunSouthwest,
+    /// Run northwest
+    RunNorthwest,
     /// Zoom out
     ZoomOut,
     /// Zoom In
@@ -48,6 +97,48 @@ pub enum Action {
 }
 
 impl Action {
+    /// Every action, in the order they are listed to the player
+    pub const ALL: [Action; 38] = [
+        Action::OpenInventory,
+        Action::OpenKeybindings,
+        Action::MessageHistory,
+        Action::CharacterSheet,
+        Action::Look,
+        Action::Travel,
+        Action::AutoExplore,
+        Action::PickUp,
+        Action::Drop,
+        Action::Throw,
+        Action::ToggleAutopickup,
+        Action::Confirm,
+        Action::Cancel,
+        Action::RemoveBinding,
+        Action::CycleTarget,
+        Action::RevealMap,
+        Action::ToggleDebug,
+        Action::ToggleConsole,
+        Action::ToggleAnimations,
+        Action::Wait,
+        Action::WalkNorth,
+        Action::WalkEast,
+        Action::WalkSouth,
+        Action::WalkWest,
+        Action::WalkNortheast,
+        Action::WalkSoutheast,
+        Action::WalkSouthwest,
+        Action::WalkNorthwest,
+        Action::RunNorth,
+        Action::RunEast,
+        Action::RunSouth,
+        Action::RunWest,
+        Action::RunNortheast,
+        Action::RunSoutheast,
+        Action::RunSouthwest,
+        Action::RunNorthwest,
+        Action::ZoomOut,
+        Action::ZoomIn,
+    ];
+
     /// Returns true if this Action should only respond to `just_pressed` events
     const fn is_toggle(&self) -> bool {
         // use Action::*;
@@ -57,6 +148,18 @@ impl Action {
         true
     }
 
+    /// Returns true if this Action works even while the game is busy, e.g. playing animations
+    ///
+    /// None of these touch the dungeon, so they can't get in the way of the turn being played out.
+    const fn is_global(&self) -> bool {
+        use Action::*;
+
+        matches!(
+            *self,
+            Cancel | ToggleDebug | ToggleAnimations | ZoomOut | ZoomIn
+        )
+    }
+
     /// Returns true if this Action ends the player's turn
     const fn ends_turn(&self) -> bool {
         use Action::*;
@@ -89,7 +192,10 @@ pub enum ActionModifier {
 }
 
 impl ActionModifier {
-    fn key_codes(&self) -> [KeyCode; 2] {
+    /// Every modifier, in order of precedence when several are held at once
+    pub const ALL: [ActionModifier; 3] = [Self::Shift, Self::Ctrl, Self::Alt];
+
+    pub fn key_codes(&self) -> [KeyCode; 2] {
         match *self {
             Self::Shift => [KeyCode::ShiftLeft, KeyCode::ShiftRight],
             Self::Ctrl => [KeyCode::ControlLeft, KeyCode::ControlRight],
@@ -119,35 +225,18 @@ impl Actions {
         self.state.get(&action).copied().unwrap_or(false)
     }
 
+    /// Whether any action at all is being performed
+    pub fn any(&self) -> bool {
+        self.state.values().any(|&state| state)
+    }
+
     /// Update actions state from current keyboard input
-    fn update(&mut self, keys: &Input<KeyCode>) -> bool {
+    fn update(&mut self, keys: &Input<KeyCode>, keymap: &KeyMap) -> bool {
         let mut received_player_input = false;
-        let bindings = KeyMap::default().action_keys();
-
-        // The "Big Three" modifier keys
-        let modifiers = [
-            keys.any_pressed(ActionModifier::Shift.key_codes()),
-            keys.any_pressed(ActionModifier::Ctrl.key_codes()),
-            keys.any_pressed(ActionModifier::Alt.key_codes()),
-        ];
-        let any_modifier = modifiers.iter().any(|&pressed| pressed);
-
-        for (&action, boundkeys) in bindings.iter() {
-            let state = if boundkeys.is_empty() {
-                false
-            } else if action.is_toggle() {
-                boundkeys.iter().any(|&boundkey| match boundkey {
-                    BoundKey::Key(keycode) => !any_modifier && keys.just_pressed(keycode),
-                    BoundKey::ModifiedKey { key, with } => {
-                        modifiers[with] && keys.just_pressed(key)
-                    }
-                })
-            } else {
-                boundkeys.iter().any(|&boundkey| match boundkey {
-                    BoundKey::Key(key) => !any_modifier && keys.pressed(key),
-                    BoundKey::ModifiedKey { key, with } => modifiers[with] && keys.pressed(key),
-                })
-            };
+        let modifiers = held_modifiers(keys);
+
+        for (&action, boundkeys) in keymap.action_keys().iter() {
+            let state = is_pressed(action, boundkeys, keys, modifiers);
 
             self.state.insert(action, state);
             received_player_input |= state && action.ends_turn();
@@ -155,15 +244,108 @@ impl Actions {
 
         received_player_input
     }
+
+    /// Update only the actions that work while the game is busy, e.g. playing animations
+    ///
+    /// Turn-ending actions keep their state, as the turn they started is still being played out,
+    /// while everything else is cleared so it doesn't go off again and again in the meantime.
+    fn update_global(&mut self, keys: &Input<KeyCode>, keymap: &KeyMap) {
+        let modifiers = held_modifiers(keys);
+
+        for (&action, boundkeys) in keymap.action_keys().iter() {
+            if action.is_global() {
+                self.state
+                    .insert(action, is_pressed(action, boundkeys, keys, modifiers));
+            } else if !action.ends_turn() {
+                self.state.insert(action, false);
+            }
+        }
+    }
+}
+
+/// Which of the "Big Three" modifier keys are held down
+fn held_modifiers(keys: &Input<KeyCode>) -> [bool; 3] {
+    [
+        keys.any_pressed(ActionModifier::Shift.key_codes()),
+        keys.any_pressed(ActionModifier::Ctrl.key_codes()),
+        keys.any_pressed(ActionModifier::Alt.key_codes()),
+    ]
+}
+
+/// Whether any of the keys bound to an action are pressed
+fn is_pressed(
+    action: Action,
+    boundkeys: &[BoundKey],
+    keys: &Input<KeyCode>,
+    modifiers: [bool; 3],
+) -> bool {
+    let any_modifier = modifiers.iter().any(|&pressed| pressed);
+
+    if action.is_toggle() {
+        boundkeys.iter().any(|&boundkey| match boundkey {
+            BoundKey::Key(keycode) => !any_modifier && keys.just_pressed(keycode),
+            BoundKey::ModifiedKey { key, with } => modifiers[with] && keys.just_pressed(key),
+        })
+    } else {
+        boundkeys.iter().any(|&boundkey| match boundkey {
+            BoundKey::Key(key) => !any_modifier && keys.pressed(key),
+            BoundKey::ModifiedKey { key, with } => modifiers[with] && keys.pressed(key),
+        })
+    }
 }
 
 /// Update the [`Actions`] resource based on key presses
+///
+/// Turn-ending actions only end the player's turn from the main game UI while the game is running;
+/// other screens (e.g. the targeting cursor or menus) reuse those same keys for their own purposes.
 pub fn update_actions(
     mut actions: ResMut<Actions>,
     keys: Res<Input<KeyCode>>,
+    keymap: Res<KeyMap>,
+    game_state: Res<State<GameState>>,
+    ui_state: Res<State<GameUi>>,
     mut next_state: ResMut<NextState<TurnState>>,
 ) {
-    if actions.update(keys.as_ref()) {
+    let received_player_input = actions.update(keys.as_ref(), keymap.as_ref());
+
+    if *ui_state == GameUi::Console {
+        // Everything typed into the console is text, bar the key to close it again
+        actions
+            .state
+            .retain(|&action, _| action == Action::ToggleConsole);
+    } else if received_player_input
+        && *game_state == GameState::Running
+        && *ui_state == GameUi::Main
+    {
         next_state.set(TurnState::PlayerTurn);
     }
 }
+
+/// Update the [`Actions`] that work while the game is busy, e.g. so animations can be turned off
+/// while they're playing
+pub fn update_global_actions(
+    mut actions: ResMut<Actions>,
+    keys: Res<Input<KeyCode>>,
+    keymap: Res<KeyMap>,
+    ui_state: Res<State<GameUi>>,
+) {
+    actions.update_global(keys.as_ref(), keymap.as_ref());
+
+    if *ui_state == GameUi::Console {
+        actions
+            .state
+            .retain(|&action, _| action == Action::ToggleConsole);
+    }
+}
+
+/// Load the keymap, letting the player know about any problems with their own bindings
+pub fn load_keymap(mut commands: Commands, mut messages: ResMut<Messages>) {
+    let (keymap, errors) = KeyMap::load();
+
+    for error in errors {
+        error!("{error}");
+        messages.add_hostile(error.to_string());
+    }
+
+    commands.insert_resource(keymap);
+}
@@ -1,23 +1,34 @@
 //! The Keymap data structure stores mapping of actions to their bound keys
 
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use bevy::prelude::*;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use super::{Action, ActionModifier};
+use crate::utils::get_config_path;
 
 /// The default keymap
 const DEFAULT_KEYMAP: &str = include_str!("default_keymap.yaml");
 
 /// A bound keycode with a modifier key (Shift/Ctrl/Alt)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum BoundKey {
     Key(KeyCode),
     ModifiedKey { key: KeyCode, with: ActionModifier },
 }
 
+impl std::fmt::Display for BoundKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoundKey::Key(key) => write!(f, "{key:?}"),
+            BoundKey::ModifiedKey { key, with } => write!(f, "{with:?}+{key:?}"),
+        }
+    }
+}
+
 /// A representation of bound keycodes
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -47,26 +58,432 @@ impl From<ActionKeys> for Vec<BoundKey> {
 }
 
 /// A set of all actions and their keybindings
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
 pub struct KeyMap {
     /// The collection of actions and keybindings
-    boundkeys: HashMap<Action, ActionKeys>,
+    boundkeys: HashMap<Action, Vec<BoundKey>>,
 }
 
 impl Default for KeyMap {
     fn default() -> Self {
+        let boundkeys: HashMap<Action, ActionKeys> = serde_yaml::from_str(DEFAULT_KEYMAP).unwrap();
+
         Self {
-            boundkeys: serde_yaml::from_str(DEFAULT_KEYMAP).unwrap(),
+            boundkeys: boundkeys
+                .into_iter()
+                .map(|(action, keys)| (action, keys.into()))
+                .collect(),
         }
     }
 }
 
 impl KeyMap {
-    /// Retrieve a map of actions to their bound keys
-    pub fn action_keys(&self) -> HashMap<Action, Vec<BoundKey>> {
-        self.boundkeys
+    /// Load the default keymap, merged with the user's keymap file if they have one
+    ///
+    /// Any problems with the user's keymap are returned alongside the keymap; bindings with
+    /// problems are skipped, leaving the default binding for that action in place.
+    pub fn load() -> (Self, Vec<KeyMapError>) {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+
+        if let Some(path) = user_keymap_path().filter(|path| path.exists()) {
+            match read_overrides(path) {
+                Ok(overrides) => keymap.merge(overrides, &mut errors),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        errors.extend(keymap.conflicts());
+
+        (keymap, errors)
+    }
+
+    /// Override bindings with those from a user keymap, collecting any errors
+    fn merge(&mut self, overrides: HashMap<String, RawActionKeys>, errors: &mut Vec<KeyMapError>) {
+        // Sort by name so that errors are reported in a consistent order
+        for (name, raw_keys) in overrides.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+            let Some(action) = parse_name::<Action>(&name) else {
+                errors.push(KeyMapError::UnknownAction { action: name });
+                continue;
+            };
+
+            let keys: Result<Vec<_>, _> = raw_keys
+                .into_keys()
+                .into_iter()
+                .map(|raw_key| raw_key.parse(&name))
+                .collect();
+            match keys {
+                Ok(keys) => {
+                    self.boundkeys.insert(action, keys);
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+    }
+
+    /// Find every key that is bound to more than one action
+    fn conflicts(&self) -> Vec<KeyMapError> {
+        let mut bound_to: HashMap<BoundKey, Action> = HashMap::new();
+        let mut conflicts = Vec::new();
+
+        for (&action, keys) in self
+            .boundkeys
             .iter()
-            .map(|(action, keys)| (*action, keys.into()))
+            .sorted_by_key(|(action, _)| format!("{action:?}"))
+        {
+            for &key in keys {
+                match bound_to.get(&key) {
+                    Some(&other) if other != action => conflicts.push(KeyMapError::Conflict {
+                        key,
+                        actions: (other, action),
+                    }),
+                    _ => {
+                        bound_to.insert(key, action);
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// Retrieve a map of actions to their bound keys
+    pub fn action_keys(&self) -> &HashMap<Action, Vec<BoundKey>> {
+        &self.boundkeys
+    }
+
+    /// The keys bound to a single action
+    pub fn bindings(&self, action: Action) -> &[BoundKey] {
+        self.boundkeys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replace the keys bound to an action
+    pub fn set_bindings(&mut self, action: Action, keys: Vec<BoundKey>) {
+        self.boundkeys.insert(action, keys);
+    }
+
+    /// Take a key away from every action it's bound to, returning those actions
+    pub fn unbind(&mut self, key: BoundKey) -> Vec<Action> {
+        let actions = self.actions_bound_to(key);
+        for keys in self.boundkeys.values_mut() {
+            keys.retain(|&bound| bound != key);
+        }
+
+        actions
+    }
+
+    /// Every action a key is bound to
+    pub fn actions_bound_to(&self, key: BoundKey) -> Vec<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|&action| self.bindings(action).contains(&key))
             .collect()
     }
+
+    /// Write the bindings that differ from the defaults to the user's keymap file, returning
+    /// where it was written
+    ///
+    /// Everything else is left out, so later changes to the defaults still reach the player.
+    pub fn save(&self) -> Result<PathBuf, String> {
+        let path = user_keymap_path().ok_or("No configuration directory to save to")?;
+        let defaults = Self::default();
+
+        // Write actions out in a consistent order so the file is easy to read and edit
+        let mut mapping = serde_yaml::Mapping::new();
+        for action in Action::ALL
+            .into_iter()
+            .filter(|&action| self.bindings(action) != defaults.bindings(action))
+        {
+            let keys = match self.bindings(action) {
+                [] => ActionKeys::Unbound,
+                [key] => ActionKeys::Single(*key),
+                keys => ActionKeys::Multi(keys.to_vec()),
+            };
+            mapping.insert(
+                serde_yaml::to_value(action).map_err(|err| err.to_string())?,
+                serde_yaml::to_value(keys).map_err(|err| err.to_string())?,
+            );
+        }
+        let yaml = serde_yaml::to_string(&mapping).map_err(|err| err.to_string())?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(&path, yaml).map_err(|err| err.to_string())?;
+
+        Ok(path)
+    }
+}
+
+/// Where the user's keymap file lives, if we can find a config directory
+pub fn user_keymap_path() -> Option<PathBuf> {
+    get_config_path("keymap.yaml")
+}
+
+/// Read the bindings from a user keymap file, without validating them yet
+fn read_overrides(path: PathBuf) -> Result<HashMap<String, RawActionKeys>, KeyMapError> {
+    std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|yaml| serde_yaml::from_str(&yaml).map_err(|err| err.to_string()))
+        .map_err(|reason| KeyMapError::Unreadable { path, reason })
+}
+
+/// Parse a name as one of our serializable enums, e.g. an [`Action`] or a [`KeyCode`]
+fn parse_name<T: for<'de> Deserialize<'de>>(name: &str) -> Option<T> {
+    serde_yaml::from_value(serde_yaml::Value::String(name.to_owned())).ok()
+}
+
+/// The keys bound to an action, as written in a keymap file and not yet validated
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum RawActionKeys {
+    Unbound,
+    Single(RawBoundKey),
+    Multi(Vec<RawBoundKey>),
+}
+
+impl RawActionKeys {
+    fn into_keys(self) -> Vec<RawBoundKey> {
+        match self {
+            RawActionKeys::Unbound => Vec::new(),
+            RawActionKeys::Single(key) => vec![key],
+            RawActionKeys::Multi(keys) => keys,
+        }
+    }
+}
+
+/// A bound key, as written in a keymap file and not yet validated
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum RawBoundKey {
+    Key(String),
+    ModifiedKey { key: String, with: String },
+}
+
+impl RawBoundKey {
+    fn parse(self, action: &str) -> Result<BoundKey, KeyMapError> {
+        let parse_key = |key: String| {
+            parse_name::<KeyCode>(&key).ok_or_else(|| KeyMapError::UnknownKey {
+                action: action.to_owned(),
+                key,
+            })
+        };
+
+        match self {
+            RawBoundKey::Key(key) => Ok(BoundKey::Key(parse_key(key)?)),
+            RawBoundKey::ModifiedKey { key, with } => {
+                let key = parse_key(key)?;
+                let with = parse_name::<ActionModifier>(&with).ok_or_else(|| {
+                    KeyMapError::UnknownModifier {
+                        action: action.to_owned(),
+                        modifier: with,
+                    }
+                })?;
+
+                Ok(BoundKey::ModifiedKey { key, with })
+            }
+        }
+    }
+}
+
+/// A problem with the user's keymap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyMapError {
+    /// The keymap file couldn't be read, or isn't a valid keymap at all
+    Unreadable { path: PathBuf, reason: String },
+    /// An action that doesn't exist
+    UnknownAction { action: String },
+    /// A key that doesn't exist
+    UnknownKey { action: String, key: String },
+    /// A modifier that isn't Shift, Ctrl, or Alt
+    UnknownModifier { action: String, modifier: String },
+    /// The same key is bound to two different actions
+    Conflict {
+        key: BoundKey,
+        actions: (Action, Action),
+    },
+}
+
+impl std::fmt::Display for KeyMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyMapError::Unreadable { path, reason } => {
+                write!(f, "Could not read keymap {}: {reason}", path.display())
+            }
+            KeyMapError::UnknownAction { action } => {
+                write!(f, "Unknown action \"{action}\" in keymap")
+            }
+            KeyMapError::UnknownKey { action, key } => {
+                write!(f, "Unknown key \"{key}\" bound to {action}")
+            }
+            KeyMapError::UnknownModifier { action, modifier } => write!(
+                f,
+                "Unknown modifier \"{modifier}\" bound to {action}; expected Shift, Ctrl, or Alt"
+            ),
+            KeyMapError::Conflict {
+                key,
+                actions: (first, second),
+            } => write!(f, "{key} is bound to both {first:?} and {second:?}"),
+        }
+    }
+}
+
+impl std::error::Error for KeyMapError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a user keymap and merge it over the defaults, as [`KeyMap::load`] does
+    fn load_str(yaml: &str) -> (KeyMap, Vec<KeyMapError>) {
+        let mut keymap = KeyMap::default();
+        let mut errors = Vec::new();
+        keymap.merge(serde_yaml::from_str(yaml).unwrap(), &mut errors);
+        errors.extend(keymap.conflicts());
+
+        (keymap, errors)
+    }
+
+    #[test]
+    fn default_keymap_has_no_conflicts() {
+        assert_eq!(KeyMap::default().conflicts(), Vec::new());
+    }
+
+    #[test]
+    fn override_replaces_default() {
+        let (keymap, errors) = load_str("Wait: [Period, {key: W, with: Ctrl}]");
+
+        assert_eq!(errors, Vec::new());
+        assert_eq!(
+            keymap.action_keys()[&Action::Wait],
+            vec![
+                BoundKey::Key(KeyCode::Period),
+                BoundKey::ModifiedKey {
+                    key: KeyCode::W,
+                    with: ActionModifier::Ctrl
+                },
+            ]
+        );
+        // Everything else keeps its default
+        assert_eq!(
+            keymap.action_keys()[&Action::WalkNorth],
+            KeyMap::default().action_keys()[&Action::WalkNorth]
+        );
+    }
+
+    #[test]
+    fn override_can_unbind() {
+        let (keymap, errors) = load_str("Wait:");
+
+        assert_eq!(errors, Vec::new());
+        assert_eq!(keymap.action_keys()[&Action::Wait], Vec::new());
+    }
+
+    #[test]
+    fn unknown_action() {
+        let (keymap, errors) = load_str("Dance: Z");
+
+        assert_eq!(
+            errors,
+            vec![KeyMapError::UnknownAction {
+                action: "Dance".to_owned()
+            }]
+        );
+        assert_eq!(keymap, KeyMap::default());
+    }
+
+    #[test]
+    fn unknown_key_keeps_default() {
+        let (keymap, errors) = load_str("Wait: [Period, Spacebar]");
+
+        assert_eq!(
+            errors,
+            vec![KeyMapError::UnknownKey {
+                action: "Wait".to_owned(),
+                key: "Spacebar".to_owned()
+            }]
+        );
+        assert_eq!(
+            keymap.action_keys()[&Action::Wait],
+            KeyMap::default().action_keys()[&Action::Wait]
+        );
+    }
+
+    #[test]
+    fn unknown_modifier() {
+        let (_, errors) = load_str("Wait: {key: W, with: Super}");
+
+        assert_eq!(
+            errors,
+            vec![KeyMapError::UnknownModifier {
+                action: "Wait".to_owned(),
+                modifier: "Super".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn conflicting_bindings() {
+        let (_, errors) = load_str("Wait: K");
+
+        assert_eq!(
+            errors,
+            vec![KeyMapError::Conflict {
+                key: BoundKey::Key(KeyCode::K),
+                actions: (Action::Wait, Action::WalkNorth)
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "K is bound to both Wait and WalkNorth"
+        );
+    }
+
+    #[test]
+    fn same_key_on_one_action_is_not_a_conflict() {
+        let (_, errors) = load_str("Wait: [Period, Period]");
+
+        assert_eq!(errors, Vec::new());
+    }
+
+    #[test]
+    fn errors_are_sorted_by_action() {
+        let (_, errors) = load_str("Wait: Spacebar\nDance: Z");
+
+        assert_eq!(
+            errors,
+            vec![
+                KeyMapError::UnknownAction {
+                    action: "Dance".to_owned()
+                },
+                KeyMapError::UnknownKey {
+                    action: "Wait".to_owned(),
+                    key: "Spacebar".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_keymap() {
+        let path = std::env::temp_dir().join(format!("keymap-test-{}.yaml", std::process::id()));
+        std::fs::write(&path, "- not\n- a keymap").unwrap();
+        let result = read_overrides(path.clone());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(KeyMapError::Unreadable { path: error_path, .. }) if error_path == path
+        ));
+    }
+
+    #[test]
+    fn missing_keymap_is_unreadable() {
+        let path = std::env::temp_dir().join("keymap-test-missing.yaml");
+
+        assert!(matches!(
+            read_overrides(path),
+            Err(KeyMapError::Unreadable { .. })
+        ));
+    }
 }
@@ -2,31 +2,44 @@ use std::{collections::HashSet, ops::Index};
 
 use crate::{
     dungeon::TilePos,
+    input_manager::{Action, Actions, InputManager},
     items::{ItemId, ItemList, UseItem},
+    magic::CastSpellOn,
     setup::Player,
-    ui::{Messages, RedrawInventoryUi},
+    ui::{GameUi, Messages, RedrawInventoryUi},
+    GameState, TurnState,
 };
 use bevy::{ecs::query::Has, prelude::*};
+use itertools::Itertools;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Component)]
 pub struct InventoryIdx(usize);
 
+/// Some number of the same item, taking up a single slot in an inventory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+}
+
+/// What something is carrying, with its capacity counted in stacks of items rather than single
+/// items
 #[derive(Debug, Default, Clone, Component)]
 pub struct Inventory {
     capacity: usize,
-    items: Vec<ItemId>,
+    stacks: Vec<ItemStack>,
 }
 
 impl Index<InventoryIdx> for Inventory {
-    type Output = ItemId;
+    type Output = ItemStack;
 
     fn index(&self, index: InventoryIdx) -> &Self::Output {
-        &self.items[index.0]
+        &self.stacks[index.0]
     }
 }
 
 impl Index<&InventoryIdx> for Inventory {
-    type Output = ItemId;
+    type Output = ItemStack;
 
     fn index(&self, index: &InventoryIdx) -> &Self::Output {
         &self[*index]
@@ -37,18 +50,18 @@ impl Inventory {
     pub fn new(capacity: usize) -> Self {
         Self {
             capacity,
-            items: Vec::with_capacity(capacity),
+            stacks: Vec::with_capacity(capacity),
         }
     }
 
-    pub fn get(&self, idx: InventoryIdx) -> Option<&ItemId> {
-        self.items.get(idx.0)
+    pub fn get(&self, idx: InventoryIdx) -> Option<&ItemStack> {
+        self.stacks.get(idx.0)
     }
 
     pub fn find(&self, item_id: ItemId) -> Option<InventoryIdx> {
-        self.items
+        self.stacks
             .iter()
-            .position(|item| *item == item_id)
+            .position(|stack| stack.item == item_id)
             .map(InventoryIdx)
     }
 
@@ -56,88 +69,275 @@ impl Inventory {
         self.capacity
     }
 
+    /// How many slots are in use, i.e. the number of stacks
     pub fn size(&self) -> usize {
-        self.items.len()
+        self.stacks.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.items.is_empty()
+        self.stacks.is_empty()
     }
 
     pub fn is_full(&self) -> bool {
         self.size() >= self.capacity()
     }
 
-    pub fn items(&self) -> &[ItemId] {
-        self.items.as_ref()
+    pub fn stacks(&self) -> &[ItemStack] {
+        self.stacks.as_ref()
     }
 
-    pub fn enumerate(&self) -> impl Iterator<Item = (InventoryIdx, &ItemId)> {
-        self.items
+    pub fn enumerate(&self) -> impl Iterator<Item = (InventoryIdx, &ItemStack)> {
+        self.stacks
             .iter()
             .enumerate()
-            .map(|(i, item)| (InventoryIdx(i), item))
+            .map(|(i, stack)| (InventoryIdx(i), stack))
     }
 
-    pub fn insert(&mut self, item: ItemId) -> bool {
-        if self.items.len() >= self.capacity {
-            false
+    /// Add an item onto a stack of it with room to spare, or else into a slot of its own
+    ///
+    /// Returns whether there was room for it.
+    pub fn insert(&mut self, item: ItemId, item_list: &ItemList) -> bool {
+        let stack_size = item_list[item].stack_size;
+        if let Some(stack) = self
+            .stacks
+            .iter_mut()
+            .find(|stack| stack.item == item && stack.count < stack_size)
+        {
+            stack.count += 1;
+        } else if self.is_full() {
+            return false;
+        } else {
+            self.stacks.push(ItemStack { item, count: 1 });
+            self.stacks.sort_unstable();
+        }
+
+        true
+    }
+
+    /// Take a single item off a stack, emptying its slot once there are none left
+    pub fn remove(&mut self, idx: InventoryIdx) -> ItemId {
+        let stack = &mut self.stacks[idx.0];
+        stack.count -= 1;
+        if stack.count > 0 {
+            stack.item
         } else {
-            self.items.push(item);
-            self.items.sort_unstable();
+            self.stacks.remove(idx.0).item
+        }
+    }
+}
+
+/// An item put down on purpose, which autopickup leaves where it is
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Dropped;
+
+/// The kinds of item the player would rather pick up themselves, if at all
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct Autopickup {
+    disabled: HashSet<ItemId>,
+}
+
+impl Autopickup {
+    pub fn is_enabled(&self, item: ItemId) -> bool {
+        !self.disabled.contains(&item)
+    }
 
+    /// Switch autopickup on or off for a kind of item, returning whether it's now on
+    pub fn toggle(&mut self, item: ItemId) -> bool {
+        if self.disabled.remove(&item) {
             true
+        } else {
+            self.disabled.insert(item);
+            false
         }
     }
+}
 
-    pub fn remove(&mut self, idx: InventoryIdx) -> ItemId {
-        self.items.remove(idx.0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct DropItem {
+    pub item: ItemId,
+    pub dropper: Entity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub struct PickUpItems {
+    pub picker: Entity,
+    /// The items to pick up, which must all be on the picker's tile
+    pub items: Vec<Entity>,
+}
+
+/// Move an item from the floor into an inventory, returning whether there was room for it
+fn take_item(
+    (item, item_id): (Entity, ItemId),
+    (inventory, is_player): (&mut Inventory, bool),
+    commands: &mut Commands,
+    item_list: &ItemList,
+    messages: &mut Messages,
+) -> bool {
+    let item_name = &item_list[item_id].name;
+    if inventory.insert(item_id, item_list) {
+        commands.entity(item).despawn();
+        if is_player {
+            messages.add_friendly(format!("Picked up {item_name}"));
+        }
+        true
+    } else {
+        if is_player {
+            messages.add(format!("You have no room for the {item_name}"));
+        }
+        false
     }
 }
 
 fn autopickup(
-    mut picker_upper_qry: Query<(&Transform, &mut Inventory, Has<Player>), Changed<Transform>>,
-    items_qry: Query<(Entity, &Transform, &ItemId)>,
+    mut picker_upper_qry: Query<(&TilePos, &mut Inventory, Has<Player>), Changed<TilePos>>,
+    items_qry: Query<(Entity, &TilePos, &ItemId), Without<Dropped>>,
     mut commands: Commands,
     item_list: Res<ItemList>,
+    autopickup: Res<Autopickup>,
     mut messages: ResMut<Messages>,
 ) {
     let mut picked_up = HashSet::new();
     for (pos, mut inventory, is_player) in picker_upper_qry.iter_mut() {
-        if inventory.is_full() {
-            continue;
+        for (item, item_pos, &item_id) in items_qry.iter() {
+            if pos != item_pos
+                || picked_up.contains(&item)
+                || (is_player && !autopickup.is_enabled(item_id))
+            {
+                continue;
+            }
+
+            if take_item(
+                (item, item_id),
+                (&mut inventory, is_player),
+                &mut commands,
+                &item_list,
+                &mut messages,
+            ) {
+                picked_up.insert(item);
+            }
         }
+    }
+}
 
-        let tile = TilePos::from(pos);
+/// Pick up whatever's on the player's tile, asking which to take when there's more than one kind
+/// of item there
+fn pick_up(
+    actions: Res<Actions>,
+    player_qry: Query<(Entity, &TilePos), With<Player>>,
+    items_qry: Query<(Entity, &TilePos, &ItemId)>,
+    mut pick_up_evt: EventWriter<PickUpItems>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+    mut messages: ResMut<Messages>,
+) {
+    if !actions.perform(Action::PickUp) {
+        return;
+    }
+    let Ok((player, pos)) = player_qry.get_single() else {
+        return;
+    };
 
-        for (item, item_pos, item_id) in items_qry.iter() {
-            let item_tile = TilePos::from(item_pos);
-            if tile == item_tile && !picked_up.contains(&item) {
-                picked_up.insert(item);
-                inventory.insert(*item_id);
-                commands.entity(item).despawn();
-                if is_player {
-                    let item_name = &item_list[item_id].name;
-                    messages.add_friendly(format!("Picked up {item_name}"));
-                }
+    let here = items_qry
+        .iter()
+        .filter(|&(_, item_pos, _)| item_pos == pos)
+        .collect_vec();
+    match here.iter().map(|&(_, _, item_id)| item_id).unique().count() {
+        0 => messages.add("There's nothing here to pick up."),
+        1 => pick_up_evt.send(PickUpItems {
+            picker: player,
+            items: here.into_iter().map(|(item, _, _)| item).collect(),
+        }),
+        _ => ui_state.set(GameUi::PickUp),
+    }
+}
+
+fn pick_up_items(
+    mut pick_up_evt: EventReader<PickUpItems>,
+    mut picker_upper_qry: Query<(&mut Inventory, Has<Player>)>,
+    items_qry: Query<&ItemId>,
+    mut commands: Commands,
+    item_list: Res<ItemList>,
+    mut messages: ResMut<Messages>,
+    mut next_state: ResMut<NextState<TurnState>>,
+) {
+    for event in pick_up_evt.iter() {
+        let Ok((mut inventory, is_player)) = picker_upper_qry.get_mut(event.picker) else {
+            continue;
+        };
+
+        let mut picked_up = false;
+        for &item in &event.items {
+            if let Ok(&item_id) = items_qry.get(item) {
+                picked_up |= take_item(
+                    (item, item_id),
+                    (&mut inventory, is_player),
+                    &mut commands,
+                    &item_list,
+                    &mut messages,
+                );
             }
         }
+
+        // Finding there's no room for anything doesn't take a turn
+        if picked_up {
+            next_state.set(TurnState::MonsterTurn);
+        }
+    }
+}
+
+/// Put an item down on the dropper's tile
+#[allow(clippy::too_many_arguments)]
+fn drop_item(
+    mut drop_evt: EventReader<DropItem>,
+    mut dropper_qry: Query<(&TilePos, &mut Inventory, Has<Player>)>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    item_list: Res<ItemList>,
+    mut messages: ResMut<Messages>,
+    mut redraw_evt: EventWriter<RedrawInventoryUi>,
+    mut next_state: ResMut<NextState<TurnState>>,
+) {
+    for event in drop_evt.iter() {
+        let Ok((&pos, mut inventory, is_player)) = dropper_qry.get_mut(event.dropper) else {
+            continue;
+        };
+        let Some(idx) = inventory.find(event.item) else {
+            continue;
+        };
+
+        let item = inventory.remove(idx);
+        let dropped = item_list.spawn(item, pos, &mut commands, &asset_server);
+        commands.entity(dropped).insert(Dropped);
+        if is_player {
+            messages.add(format!("You drop the {}", item_list[item].name));
+        }
+
+        redraw_evt.send_default();
+        next_state.set(TurnState::MonsterTurn);
     }
 }
 
 fn consume_item(
     mut inventory_qry: Query<&mut Inventory>,
     mut use_item_evts: EventReader<UseItem>,
+    mut cast_spell_evts: EventReader<CastSpellOn>,
     mut redraw_evt: EventWriter<RedrawInventoryUi>,
     item_list: Res<ItemList>,
 ) {
-    for event in use_item_evts.iter() {
-        if item_list[event.item].is_consumable() {
-            if let Ok(mut inventory) = inventory_qry.get_mut(event.user) {
-                if let Some(idx) = inventory.find(event.item) {
-                    inventory.remove(idx);
-                    redraw_evt.send_default();
-                }
+    let used = use_item_evts
+        .iter()
+        .filter(|event| item_list[event.item].is_consumed_on_use())
+        .map(|event| (event.user, event.item));
+    // A spell hitting several targets still only uses up its scroll once
+    let cast: HashSet<_> = cast_spell_evts
+        .iter()
+        .filter_map(|event| event.item.map(|item| (event.caster, item)))
+        .collect();
+
+    for (user, item) in used.chain(cast) {
+        if let Ok(mut inventory) = inventory_qry.get_mut(user) {
+            if let Some(idx) = inventory.find(item) {
+                inventory.remove(idx);
+                redraw_evt.send_default();
             }
         }
     }
@@ -147,6 +347,22 @@ pub struct InventoryPlugin;
 
 impl Plugin for InventoryPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (autopickup, consume_item));
+        app.init_resource::<Autopickup>()
+            .add_event::<DropItem>()
+            .add_event::<PickUpItems>()
+            .add_systems(
+                Update,
+                (
+                    autopickup,
+                    consume_item,
+                    pick_up
+                        .after(InputManager)
+                        .run_if(in_state(GameState::Running))
+                        .run_if(in_state(TurnState::WaitingForPlayer))
+                        .run_if(in_state(GameUi::Main)),
+                    pick_up_items,
+                    drop_item,
+                ),
+            );
     }
 }
@@ -1,23 +1,33 @@
-use std::{collections::HashMap, fs::File, io::BufReader, ops::Index};
+use std::{
+    collections::HashMap,
+    ops::Index,
+    path::{Path, PathBuf},
+};
 
-use bevy::prelude::*;
-use itertools::Itertools;
+use bevy::{prelude::*, utils::HashSet};
 use serde::Deserialize;
 
 use crate::{
     combat::HP,
+    dungeon::TilePos,
+    fieldofview::HideOutOfSight,
     magic::{CastSpell, Effect, Spell},
-    utils::get_dat_path,
+    mobs::Description,
+    raws::{asset_exists, NotInRaws, Raws, RawsError},
+    spawner::SpawnRule,
+    utils::SpriteLayer,
     TurnState,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Component)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
 pub struct ItemId(usize);
 
 #[derive(Debug, Clone, Resource)]
 pub struct ItemList {
     names: HashMap<String, ItemId>,
     items: Vec<ItemData>,
+    /// Items that have been taken out of the raws while the game was running
+    removed: HashSet<ItemId>,
 }
 
 impl Index<ItemId> for ItemList {
@@ -37,41 +47,214 @@ impl Index<&ItemId> for ItemList {
 }
 
 impl ItemList {
-    pub fn from_raws() -> Self {
-        let path = get_dat_path("items.yaml");
-        let reader = BufReader::new(File::open(path).unwrap());
+    /// Build the item list from every data pack's raws, in load order, checking everything in
+    /// them
+    ///
+    /// An item with the same name as one from an earlier pack replaces it.
+    pub fn from_raws<'a>(
+        all_raws: impl IntoIterator<Item = &'a Raws>,
+    ) -> Result<Self, Vec<RawsError>> {
+        let mut errors = Vec::new();
+        let mut items: Vec<ItemData> = Vec::new();
+
+        for raws in all_raws {
+            let mut in_file = HashSet::new();
+            for (document, mut item) in raws.parse::<ItemData>(&mut errors) {
+                if !in_file.insert(item.name.to_lowercase()) {
+                    errors.push(raws.error(
+                        document,
+                        format!("name: there's already an item called \"{}\"", item.name),
+                    ));
+                    continue;
+                }
+                item.sprite = raws.resolve(Path::new("sprites").join(&item.sprite));
+                if !asset_exists(&item.sprite) {
+                    errors.push(raws.error(
+                        document,
+                        format!("sprite: {} doesn't exist", item.sprite.display()),
+                    ));
+                }
+                if item.stack_size == 0 {
+                    errors.push(raws.error(document, "stack_size: must be at least 1"));
+                }
+                for (i, rule) in item.spawn.iter().enumerate() {
+                    if let Err(err) = rule.validate() {
+                        errors.push(raws.error(document, format!("spawn[{i}].{err}")));
+                    }
+                }
+
+                match items
+                    .iter_mut()
+                    .find(|other| other.name.eq_ignore_ascii_case(&item.name))
+                {
+                    Some(replaced) => {
+                        info!("{} replaces the item {}", raws.file(), replaced.name);
+                        *replaced = item;
+                    }
+                    None => items.push(item),
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
 
-        let mut items = serde_yaml::Deserializer::from_reader(reader)
-            .map(|document| ItemData::deserialize(document).unwrap())
-            .collect_vec();
         // Ensure our item list is sorted, which makes our item IDs sortable in the same order
         items.sort_unstable();
 
+        Ok(Self::numbered(items, HashSet::new()))
+    }
+
+    /// Number the items in the order given, leaving out removed items when looking up names
+    fn numbered(items: Vec<ItemData>, removed: HashSet<ItemId>) -> Self {
         let names = items
             .iter()
             .enumerate()
-            .map(|(i, item)| (item.name.to_lowercase(), ItemId(i)))
+            .map(|(i, item)| (ItemId(i), item))
+            .filter(|(item_id, _)| !removed.contains(item_id))
+            .map(|(item_id, item)| (item.name.to_lowercase(), item_id))
+            .collect();
+
+        Self {
+            names,
+            items,
+            removed,
+        }
+    }
+
+    /// Give every item the same ID it has in the list currently in use, when reloading the raws
+    ///
+    /// Items already in the game hold on to their IDs, so they'd turn into something else if they
+    /// were numbered afresh. New items are numbered after the old ones instead, and removed ones
+    /// are kept for whatever's left of them in the game, though they're never spawned again.
+    pub fn keep_ids_from(self, current: &ItemList) -> Self {
+        let mut new_items: HashMap<_, _> = self
+            .items
+            .into_iter()
+            .map(|item| (item.name.to_lowercase(), item))
             .collect();
 
-        Self { names, items }
+        let mut items = Vec::with_capacity(current.items.len());
+        let mut removed = HashSet::new();
+        for (i, old) in current.items.iter().enumerate() {
+            match new_items.remove(&old.name.to_lowercase()) {
+                Some(item) => items.push(item),
+                None => {
+                    if !current.removed.contains(&ItemId(i)) {
+                        warn!("{} is no longer in the raws", old.name);
+                    }
+                    removed.insert(ItemId(i));
+                    items.push(old.clone());
+                }
+            }
+        }
+        let mut added: Vec<_> = new_items.into_values().collect();
+        added.sort_unstable();
+        items.extend(added);
+
+        Self::numbered(items, removed)
+    }
+
+    /// Every item still in the raws, along with its ID
+    fn current(&self) -> impl Iterator<Item = (ItemId, &ItemData)> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (ItemId(i), item))
+            .filter(|(item_id, _)| !self.removed.contains(item_id))
+    }
+
+    /// The names of every item, as written in the raws
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.current().map(|(_, item)| item.name.as_str())
+    }
+
+    /// Every item along with the rules for where it turns up in the dungeon
+    pub fn spawn_rules(&self) -> impl Iterator<Item = (ItemId, &[SpawnRule])> {
+        self.current()
+            .map(|(item_id, item)| (item_id, item.spawn.as_slice()))
     }
 
-    pub fn get<S: AsRef<str>>(&self, item_name: S) -> ItemId {
-        *self.names.get(&item_name.as_ref().to_lowercase()).unwrap()
+    pub fn get<S: AsRef<str>>(&self, item_name: S) -> Result<ItemId, NotInRaws> {
+        self.names
+            .get(&item_name.as_ref().to_lowercase())
+            .copied()
+            .ok_or_else(|| NotInRaws::new("item", item_name.as_ref()))
+    }
+
+    /// Place an item on the floor of the dungeon
+    pub fn spawn(
+        &self,
+        item: ItemId,
+        pos: TilePos,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+    ) -> Entity {
+        self[item].spawn(item, pos, commands, asset_server)
     }
 }
 
+// NOTE: Unknown fields can't be denied here, as they're all passed along to the flattened `Item`
+// to sort out, so that's where they're denied instead
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct ItemData {
     pub name: String,
+    /// Path to the sprite within the assets directory, once the item list has been built
+    sprite: PathBuf,
+    #[serde(default)]
+    pub description: String,
+    /// How many of the item fit in a single inventory slot
+    #[serde(default = "default_stack_size")]
+    pub stack_size: u32,
+    #[serde(default)]
+    spawn: Vec<SpawnRule>,
     #[serde(flatten)]
     pub data: Item,
 }
 
 impl ItemData {
+    fn spawn(
+        &self,
+        item: ItemId,
+        pos: TilePos,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+    ) -> Entity {
+        let mut ec = commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(self.sprite()),
+                transform: pos.as_transform(SpriteLayer::Item),
+                ..Default::default()
+            },
+            pos,
+            Name::new(self.name.clone()),
+            HideOutOfSight::Hide,
+            item,
+        ));
+
+        if !self.description.is_empty() {
+            ec.insert(Description(self.description.clone()));
+        }
+
+        ec.id()
+    }
+
+    pub fn sprite(&self) -> PathBuf {
+        self.sprite.clone()
+    }
+
     pub fn is_consumable(&self) -> bool {
         self.data.is_consumable()
     }
+
+    pub fn is_consumed_on_use(&self) -> bool {
+        self.data.is_consumed_on_use()
+    }
+}
+
+fn default_stack_size() -> u32 {
+    1
 }
 
 impl PartialOrd for ItemData {
@@ -87,7 +270,7 @@ impl Ord for ItemData {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Deserialize)]
-#[serde(tag = "type", rename_all = "lowercase")]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
 pub enum Item {
     Potion { effect: Effect },
     Scroll { spell: Spell },
@@ -99,6 +282,13 @@ impl Item {
     pub fn is_consumable(&self) -> bool {
         matches!(self, Item::Potion { .. } | Item::Scroll { .. })
     }
+
+    /// Whether using this item immediately uses it up
+    ///
+    /// Scrolls are consumable but are only used up once their spell is actually cast.
+    pub fn is_consumed_on_use(&self) -> bool {
+        matches!(self, Item::Potion { .. })
+    }
 }
 
 #[derive(Debug, Clone, Copy, Event)]
@@ -123,6 +313,7 @@ fn use_item(
                 Item::Scroll { spell } => cast_spell_evt.send(CastSpell {
                     caster: event.user,
                     spell,
+                    item: Some(event.item),
                 }),
                 Item::Weapon => todo!(),
                 Item::Armor => todo!(),
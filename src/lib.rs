@@ -1,12 +1,18 @@
+use std::time::Duration;
+
 use bevy::{
+    asset::ChangeWatcher,
     prelude::*,
     window::{Window, WindowMode, WindowPlugin},
     DefaultPlugins,
 };
 
+pub mod animation;
 pub mod bump;
 pub mod camera;
 pub mod combat;
+pub mod console;
+pub mod debug;
 pub mod dungeon;
 pub mod fieldofview;
 pub mod input_manager;
@@ -14,10 +20,15 @@ pub mod inventory;
 pub mod items;
 pub mod magic;
 pub mod mobs;
+pub mod morgue;
 pub mod movement;
 pub mod rand;
+pub mod raws;
+pub mod record;
 pub mod setup;
+pub mod spawner;
 pub mod stats;
+pub mod travel;
 pub mod ui;
 pub mod utils;
 
@@ -29,6 +40,9 @@ pub enum GameState {
     AssetsLoading,
     Setup,
     Running,
+    /// Heading down to the next level of the dungeon
+    Descending,
+    GameOver,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, States)]
@@ -39,17 +53,7 @@ pub enum TurnState {
     MonsterTurn,
 }
 
-fn state_manager(state: Res<State<GameState>>, mut next_state: ResMut<NextState<GameState>>) {
-    // FIXME: Temporary system to "skip" states we're not utilizing yet
-    #[allow(clippy::single_match)]
-    match **state {
-        // GameState::Starting => next_state.set(GameState::MainMenu),
-        GameState::MainMenu => next_state.set(GameState::AssetsLoading),
-        GameState::AssetsLoading => next_state.set(GameState::Setup), // FIXME: Load assets at startup
-        // GameState::Setup => next_state.set(GameState::Running),
-        _ => {}
-    };
-
+fn state_manager(state: Res<State<GameState>>, next_state: Res<NextState<GameState>>) {
     if let Some(next) = next_state.0 {
         info!("Switching from {state:?} to state {next:?}");
     }
@@ -59,15 +63,26 @@ fn state_manager(state: Res<State<GameState>>, mut next_state: ResMut<NextState<
 pub fn run() {
     App::new()
         .insert_resource(ClearColor(Color::BLACK))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Swords and Runes".to_string(),
-                mode: WindowMode::BorderlessFullscreen,
-                ..Default::default()
-            }),
-            ..Default::default()
-        }))
-        .add_systems(Update, bevy::window::close_on_esc)
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "Swords and Runes".to_string(),
+                        mode: WindowMode::BorderlessFullscreen,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .set(AssetPlugin {
+                    // Watching for changes needs the `hot_reload` feature, and panics without it
+                    watch_for_changes: if cfg!(feature = "hot_reload") {
+                        ChangeWatcher::with_delay(Duration::from_millis(200))
+                    } else {
+                        None
+                    },
+                    ..Default::default()
+                }),
+        )
         // Begin game configuration
         .add_state::<GameState>()
         .add_state::<TurnState>()
@@ -80,17 +95,26 @@ pub fn run() {
             ),
         )
         .add_plugins((
+            animation::AnimationPlugin,
             bump::BumpPlugin,
             camera::CameraPlugin,
             combat::CombatPlugin,
+            console::ConsolePlugin,
+            debug::DebugPlugin,
             dungeon::DungeonPlugin,
             items::ItemsPlugin,
             input_manager::InputManagerPlugin,
             inventory::InventoryPlugin,
             magic::MagicPlugin,
             mobs::MobsPlugin,
+            morgue::MorguePlugin,
             movement::MovementPlugin,
+        ))
+        .add_plugins((
+            raws::RawsPlugin,
+            record::RecordPlugin,
             setup::SetupPlugin,
+            travel::TravelPlugin,
             ui::UIPlugin,
         ))
         .run();
@@ -12,6 +12,16 @@ pub enum Effect {
     Harm(u16),
 }
 
+impl Effect {
+    /// Color used to show this effect, e.g. in spell animations
+    pub fn color(&self) -> Color {
+        match *self {
+            Effect::Heal(_) => Color::GREEN,
+            Effect::Harm(_) => Color::ORANGE_RED,
+        }
+    }
+}
+
 pub fn apply_effect(effect: Effect, hp: &mut HP) {
     match effect {
         Effect::Heal(heal) => hp.add(heal),
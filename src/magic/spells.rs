@@ -1,11 +1,17 @@
 use bevy::prelude::*;
 use serde::Deserialize;
 
-use crate::{combat::HP, ui::GameUi};
+use crate::{
+    combat::{LastHurtBy, HP},
+    items::ItemId,
+    ui::GameUi,
+    TurnState,
+};
 
 use super::{apply_effect, Effect};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Spell {
     pub target: SpellTarget,
     pub range: u8,
@@ -24,16 +30,23 @@ pub enum SpellTarget {
 pub struct CastSpell {
     pub caster: Entity,
     pub spell: Spell,
+    /// The item (e.g. a scroll) the spell is being cast from, if any
+    pub item: Option<ItemId>,
 }
 
 impl CastSpell {
     pub fn on(&self, target: Entity) -> CastSpellOn {
-        let Self { caster, spell } = *self;
+        let Self {
+            caster,
+            spell,
+            item,
+        } = *self;
 
         CastSpellOn {
             caster,
             target,
             spell,
+            item,
         }
     }
 }
@@ -43,6 +56,8 @@ pub struct CastSpellOn {
     pub caster: Entity,
     pub target: Entity,
     pub spell: Spell,
+    /// The item the spell was cast from; it is used up once the spell goes off
+    pub item: Option<ItemId>,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
@@ -82,15 +97,22 @@ pub(super) fn cast_spell(
 }
 
 pub(super) fn cast_spell_on(
+    mut commands: Commands,
     mut cast_spell_on_evt: EventReader<CastSpellOn>,
     mut health_qry: Query<&mut HP>,
     mut spell_to_cast: ResMut<SpellToCast>,
+    mut next_state: ResMut<NextState<TurnState>>,
 ) {
     for cast in cast_spell_on_evt.iter() {
         if let Ok(mut hp) = health_qry.get_mut(cast.target) {
             apply_effect(cast.spell.effect, &mut hp);
+            if let Effect::Harm(_) = cast.spell.effect {
+                commands.entity(cast.target).insert(LastHurtBy(cast.caster));
+            }
         }
 
         spell_to_cast.clear();
+        // Casting a spell takes the caster's turn
+        next_state.set(TurnState::MonsterTurn);
     }
 }
@@ -1,5 +1,33 @@
-fn main() {
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // Check the raws without starting the game, e.g. after editing them
+    if std::env::args().skip(1).any(|arg| arg == "--check-raws") {
+        let (packs, problems) = swordsandrunes::raws::data_packs();
+        for problem in problems {
+            eprintln!("{problem}");
+        }
+        for pack in &packs {
+            let manifest = &pack.manifest;
+            println!("Checking {} {}", manifest.name, manifest.version);
+        }
+
+        let errors = swordsandrunes::raws::check_raws(&packs);
+        for err in &errors {
+            eprintln!("{err}");
+        }
+
+        return if errors.is_empty() {
+            println!("The raws look good!");
+            ExitCode::SUCCESS
+        } else {
+            eprintln!("Found {} problem(s) with the raws", errors.len());
+            ExitCode::FAILURE
+        };
+    }
+
     println!("Hello, world!");
     swordsandrunes::run();
     println!("Goodbye, world!");
+    ExitCode::SUCCESS
 }
@@ -1,58 +1,216 @@
-use bevy::{prelude::*, utils::HashSet};
+use bevy::{ecs::query::Has, prelude::*, utils::HashSet};
 use serde::Deserialize;
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use crate::{
+    animation::animations_finished,
     combat::{AttackEvent, HP},
     dungeon::{BlocksMovement, Map, TilePos},
     fieldofview::{FieldOfView, HideOutOfSight},
+    rand::prelude::*,
+    raws::{asset_exists, NotInRaws, Raws, RawsError},
+    record::RunRecord,
     setup::Player,
+    spawner::{GroupSize, SpawnRule},
     stats::{Attributes, Skill, SkillSheet},
-    utils::{get_dat_path, SpriteLayer},
+    utils::SpriteLayer,
     TurnState,
 };
 
+/// How far a follower lets its leader get away before catching back up
+const FOLLOW_DISTANCE: u32 = 2;
+/// The chance of a leader wandering off somewhere on a turn it has nothing better to do
+const WANDER_CHANCE: f64 = 0.3;
+
 /// Marker component for mobs
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
 pub struct Mob;
 
+/// Where a monster is heading and the way it means to get there, as worked out on its last turn
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct AiTarget {
+    pub target: TilePos,
+    pub path: Vec<TilePos>,
+}
+
+/// Marker component for a monster that others follow around, e.g. the captain of a warband
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Leader;
+
+/// A monster that keeps close to its leader, and joins in as soon as the leader spots the player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Follower {
+    pub leader: Entity,
+}
+
+/// A few words about what something is, shown when it's examined
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct Description(pub String);
+
+/// What a monster fights with, e.g. "a crude scimitar"
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct Wields(pub String);
+
 #[derive(Debug, Deserialize, Resource)]
 pub struct MobList {
     mobs: HashMap<String, MobData>,
 }
 
 impl MobList {
-    pub fn from_raws() -> Self {
-        let path = get_dat_path("mobs.yaml");
-        let reader = BufReader::new(File::open(path).unwrap());
-
-        Self {
-            mobs: serde_yaml::Deserializer::from_reader(reader)
-                .map(|document| {
-                    let mob = MobData::deserialize(document).unwrap();
-                    (mob.name.to_lowercase(), mob)
-                })
-                .collect(),
+    /// Build the mob list from every data pack's raws, in load order, checking everything in them
+    ///
+    /// A mob with the same name as one from an earlier pack replaces it.
+    pub fn from_raws<'a>(
+        all_raws: impl IntoIterator<Item = &'a Raws>,
+    ) -> Result<Self, Vec<RawsError>> {
+        let mut errors = Vec::new();
+        let mut mobs = HashMap::new();
+        let mut followed = Vec::new();
+
+        for raws in all_raws {
+            let mut in_file = HashSet::new();
+            for (document, mut mob) in raws.parse::<MobData>(&mut errors) {
+                let key = mob.name.to_lowercase();
+                if !in_file.insert(key.clone()) {
+                    errors.push(raws.error(
+                        document,
+                        format!("name: there's already a mob called \"{}\"", mob.name),
+                    ));
+                    continue;
+                }
+                mob.sprite = raws.resolve(Path::new("sprites").join(&mob.sprite));
+                if !asset_exists(&mob.sprite) {
+                    errors.push(raws.error(
+                        document,
+                        format!("sprite: {} doesn't exist", mob.sprite.display()),
+                    ));
+                }
+                for (i, rule) in mob.spawn.iter().enumerate() {
+                    if let Err(err) = rule.validate() {
+                        errors.push(raws.error(document, format!("spawn[{i}].{err}")));
+                    }
+                }
+                followed.extend(
+                    mob.followers
+                        .iter()
+                        .enumerate()
+                        .map(|(i, followers)| (raws, document, i, followers.mob.clone())),
+                );
+
+                if let Some(replaced) = mobs.insert(key, mob) {
+                    info!("{} replaces the mob {}", raws.file(), replaced.name);
+                }
+            }
+        }
+
+        // Followers can be listed before the mob leading them, or even in a later pack, so these
+        // can only be checked last
+        for (raws, document, i, follower) in followed {
+            if !mobs.contains_key(&follower.to_lowercase()) {
+                errors.push(raws.error(
+                    document,
+                    format!("followers[{i}].mob: there's no mob called \"{follower}\""),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { mobs })
+        } else {
+            Err(errors)
         }
     }
 
+    pub fn get<S: AsRef<str>>(&self, mob_name: S) -> Result<&MobData, NotInRaws> {
+        self.mobs
+            .get(&mob_name.as_ref().to_lowercase())
+            .ok_or_else(|| NotInRaws::new("mob", mob_name.as_ref()))
+    }
+
+    /// The names of every mob, as written in the raws
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.mobs.values().map(|mob| mob.name.as_str())
+    }
+
+    /// Every mob's name along with the rules for where it turns up in the dungeon
+    pub fn spawn_rules(&self) -> impl Iterator<Item = (&str, &[SpawnRule])> {
+        self.mobs
+            .values()
+            .map(|mob| (mob.name.as_str(), mob.spawn.as_slice()))
+    }
+
     pub fn spawn<S: AsRef<str>>(
         &self,
         mob_name: S,
         commands: &mut Commands,
         asset_server: &AssetServer,
-    ) -> Entity {
-        self.mobs
-            .get(&mob_name.as_ref().to_lowercase())
-            .unwrap()
-            .spawn(commands, asset_server)
+    ) -> Result<Entity, NotInRaws> {
+        Ok(self.get(mob_name)?.spawn(commands, asset_server))
     }
+
+    /// Spawn a mob at a tile, along with any followers it leads on tiles taken from those given
+    ///
+    /// Followers take the tiles closest to their leader, and any that don't fit are left behind.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn_group<S: AsRef<str>>(
+        &self,
+        mob_name: S,
+        leader_tile: TilePos,
+        tiles: &mut Vec<TilePos>,
+        rng: &mut Random,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+    ) -> Result<Entity, NotInRaws> {
+        let mob = self.get(mob_name)?;
+        let leader = mob.spawn(commands, asset_server);
+        commands
+            .entity(leader)
+            .insert((leader_tile, leader_tile.as_transform(SpriteLayer::Actor)));
+
+        // Closest tiles last, so they're the first to be taken
+        tiles.sort_by_key(|tile| Reverse(tile.distance(leader_tile)));
+        for followers in &mob.followers {
+            for _ in 0..followers.count.roll(rng) {
+                let Some(tile) = tiles.pop() else {
+                    break;
+                };
+                let follower = self.spawn(&followers.mob, commands, asset_server)?;
+                commands.entity(follower).insert((
+                    tile,
+                    tile.as_transform(SpriteLayer::Actor),
+                    Follower { leader },
+                ));
+                commands.entity(leader).insert(Leader);
+            }
+        }
+
+        Ok(leader)
+    }
+}
+
+/// Monsters that turn up alongside a leader
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Followers {
+    mob: String,
+    #[serde(default)]
+    count: GroupSize,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MobData {
     name: String,
-    sprite: String,
+    /// Path to the sprite within the assets directory, once the mob list has been built
+    sprite: PathBuf,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    wields: Option<String>,
     #[serde(default = "default_blocks_movement")]
     blocks_movement: bool,
     #[serde(alias = "HP", alias = "hit_points")]
@@ -61,9 +219,17 @@ pub struct MobData {
     attack: Skill,
     #[serde(default = "Default::default")]
     attributes: Attributes,
+    #[serde(default)]
+    followers: Vec<Followers>,
+    #[serde(default)]
+    spawn: Vec<SpawnRule>,
 }
 
 impl MobData {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn spawn(&self, commands: &mut Commands, asset_server: &AssetServer) -> Entity {
         let mut skills = SkillSheet::new();
         skills.set("Defense", self.defense);
@@ -85,11 +251,19 @@ impl MobData {
             ec.insert(BlocksMovement);
         }
 
+        if !self.description.is_empty() {
+            ec.insert(Description(self.description.clone()));
+        }
+
+        if let Some(wields) = &self.wields {
+            ec.insert(Wields(wields.clone()));
+        }
+
         ec.id()
     }
 
     pub fn sprite(&self) -> PathBuf {
-        PathBuf::from("sprites").join(&self.sprite)
+        self.sprite.clone()
     }
 }
 
@@ -97,55 +271,99 @@ fn default_blocks_movement() -> bool {
     true
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn monster_ai(
+    mut commands: Commands,
     map: Res<Map>,
     fov_qry: Query<&FieldOfView>,
     mut attack: EventWriter<AttackEvent>,
-    mut transform_qry_set: ParamSet<(
-        Query<(Entity, &mut Transform), With<Mob>>,
-        Query<(Entity, &Transform), With<Player>>,
-        Query<&Transform, With<BlocksMovement>>,
+    mut pos_qry_set: ParamSet<(
+        Query<(Entity, &mut TilePos, Option<&Follower>, Has<Leader>), With<Mob>>,
+        Query<(Entity, &TilePos), With<Player>>,
+        Query<&TilePos, With<BlocksMovement>>,
     )>,
     mut next_state: ResMut<NextState<TurnState>>,
+    rand: Res<Random>,
+    record: Res<RunRecord>,
 ) {
     // Get the player's position first to avoid looking this up repeatedly
-    if let Ok((player, player_pos)) = transform_qry_set.p1().get_single() {
-        let player_tile = TilePos::from(player_pos);
-
+    if let Ok((player, &player_tile)) = pos_qry_set.p1().get_single() {
         // NOTE: We assume below that any mob we end up moving is the only thing blocking a given tile
         // If this assumption changes, we'll need to change this to e.g. a HashMap tracking tile positions
         // and a count of how many entities are blocking it, and then we can decrement that count when
         // moving a monster; likewise when checking if a tile is blocked, we would instead check that
         // the blocking count is >0
-        let mut unwalkable: HashSet<_> = transform_qry_set.p2().iter().map(TilePos::from).collect();
-
-        for (monster, mut monster_pos) in transform_qry_set.p0().iter_mut() {
-            let monster_tile = TilePos::from(*monster_pos);
-
-            if let Some(tile_entity) = map.get(monster_tile) {
-                if let Ok(&FieldOfView::Visible) = fov_qry.get(tile_entity) {
-                    if monster_tile.distance(player_tile) <= 1 {
-                        attack.send(AttackEvent::new(monster, player));
-                    } else if let Some((path, _)) = pathfinding::directed::astar::astar(
-                        &monster_tile,
-                        |tile| {
-                            map.neighbors_of(*tile).into_iter().filter_map(|tile| {
-                                if !unwalkable.contains(&tile) {
-                                    Some((tile, 1))
-                                } else {
-                                    None
-                                }
-                            })
-                        },
-                        |tile| tile.distance(player_tile),
-                        |tile| *tile == player_tile,
-                    ) {
-                        unwalkable.remove(&monster_tile); // We're no longer blocking this tile, assume no one else is
-                        unwalkable.insert(path[1]); // We are however blocking this next tile
-                        *monster_pos = path[1].as_transform(SpriteLayer::Actor);
-                    }
-                }
+        let mut unwalkable: HashSet<_> = pos_qry_set.p2().iter().copied().collect();
+        // Kept up to date as monsters move, so followers go after where their leader is now
+        let mut mob_tiles: HashMap<_, _> = pos_qry_set
+            .p0()
+            .iter()
+            .map(|(monster, &tile, ..)| (monster, tile))
+            .collect();
+        let mut rng = rand.from_seed(record.turn_seed());
+
+        // Visibility goes both ways: a monster on a tile the player can see can see the player too
+        let sees_player = |tile: TilePos| {
+            map.get(tile)
+                .and_then(|tile_entity| fov_qry.get(tile_entity).ok())
+                .is_some_and(|&fov| fov == FieldOfView::Visible)
+        };
+
+        for (monster, mut monster_tile, follower, is_leader) in pos_qry_set.p0().iter_mut() {
+            let leader_tile =
+                follower.and_then(|follower| mob_tiles.get(&follower.leader).copied());
+
+            // Followers converge on the player as soon as their leader spots them
+            let goal = if sees_player(*monster_tile) || leader_tile.is_some_and(sees_player) {
+                Some(player_tile)
+            } else if let Some(leader_tile) =
+                leader_tile.filter(|&tile| monster_tile.distance(tile) > FOLLOW_DISTANCE)
+            {
+                Some(leader_tile)
+            } else if is_leader && rng.gen_bool(WANDER_CHANCE) {
+                map.neighbors_of(*monster_tile)
+                    .into_iter()
+                    .filter(|tile| !unwalkable.contains(tile))
+                    .choose(&mut rng)
+            } else {
+                None
+            };
+            let Some(goal) = goal else {
+                commands.entity(monster).remove::<AiTarget>();
+                continue;
+            };
+
+            if goal == player_tile && monster_tile.distance(player_tile) <= 1 {
+                attack.send(AttackEvent::new(monster, player));
+                commands.entity(monster).insert(AiTarget {
+                    target: player_tile,
+                    path: Vec::new(),
+                });
+            } else if let Some((path, _)) = pathfinding::directed::astar::astar(
+                &*monster_tile,
+                |tile| {
+                    map.neighbors_of(*tile).into_iter().filter_map(|tile| {
+                        // A leader blocks its own tile, but can still be headed towards
+                        if !unwalkable.contains(&tile) || tile == goal {
+                            Some((tile, 1))
+                        } else {
+                            None
+                        }
+                    })
+                },
+                |tile| tile.distance(goal),
+                |tile| *tile == goal,
+            ) {
+                unwalkable.remove(&*monster_tile); // We're no longer blocking this tile, assume no one else is
+                unwalkable.insert(path[1]); // We are however blocking this next tile
+                *monster_tile = path[1];
+                mob_tiles.insert(monster, path[1]);
+                commands.entity(monster).insert(AiTarget {
+                    target: goal,
+                    path: path[1..].to_vec(),
+                });
+            } else {
+                commands.entity(monster).remove::<AiTarget>();
             }
         }
     }
@@ -159,6 +377,11 @@ pub struct MobsPlugin;
 
 impl Plugin for MobsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, monster_ai.run_if(in_state(TurnState::MonsterTurn)));
+        app.add_systems(
+            Update,
+            monster_ai
+                .run_if(in_state(TurnState::MonsterTurn))
+                .run_if(animations_finished),
+        );
     }
 }
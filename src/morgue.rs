@@ -0,0 +1,230 @@
+//! Keeping a record of finished runs
+//!
+//! When a game ends, a plain-text morgue file summing up the character and how the run went is
+//! written to the user's data directory, and the run is entered into the local high score table.
+
+use std::{
+    fmt::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inventory::Inventory,
+    items::ItemList,
+    record::RunRecord,
+    setup::Player,
+    stats::{Attributes, SkillSheet},
+    ui::Messages,
+    utils::get_data_path,
+    GameState,
+};
+
+/// How many of the last messages go in a morgue file
+const MORGUE_MESSAGES: usize = 20;
+/// How many runs the high score table keeps
+const MAX_HIGH_SCORES: usize = 10;
+
+/// A finished run, as listed in the high score table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: u32,
+    pub cause_of_death: String,
+    pub depth: u32,
+    pub turns: u32,
+    pub kills: u32,
+    pub seed: u64,
+}
+
+impl From<&RunRecord> for HighScore {
+    fn from(record: &RunRecord) -> Self {
+        Self {
+            score: record.score(),
+            cause_of_death: record
+                .cause_of_death
+                .clone()
+                .unwrap_or_else(|| "Died".to_string()),
+            depth: record.depth,
+            turns: record.turns,
+            kills: record.total_kills(),
+            seed: record.seed,
+        }
+    }
+}
+
+/// The best runs so far, best first
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HighScores(Vec<HighScore>);
+
+impl HighScores {
+    /// Load the high score table, or start a fresh one if there isn't one yet
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = high_scores_path().filter(|path| path.exists()) else {
+            return Ok(Self::default());
+        };
+
+        let yaml = std::fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        serde_yaml::from_str(&yaml).map_err(|err| err.to_string())
+    }
+
+    /// Write the high score table to the user's data directory
+    pub fn save(&self) -> Result<PathBuf, String> {
+        let path = high_scores_path().ok_or("No data directory to save to")?;
+        write_data_file(
+            &path,
+            serde_yaml::to_string(self).map_err(|err| err.to_string())?,
+        )?;
+
+        Ok(path)
+    }
+
+    /// Enter a run into the table, if it scored well enough
+    pub fn insert(&mut self, high_score: HighScore) {
+        let idx = self
+            .0
+            .partition_point(|other| other.score >= high_score.score);
+        self.0.insert(idx, high_score);
+        self.0.truncate(MAX_HIGH_SCORES);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HighScore> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+fn high_scores_path() -> Option<PathBuf> {
+    get_data_path("highscores.yaml")
+}
+
+/// Write a file, creating the directory it goes in if need be
+fn write_data_file(path: &PathBuf, contents: String) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(path, contents).map_err(|err| err.to_string())
+}
+
+/// Sum up a finished run
+fn morgue(
+    record: &RunRecord,
+    (attributes, skills, inventory): (&Attributes, &SkillSheet, &Inventory),
+    item_list: &ItemList,
+    messages: &Messages,
+) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    let heading =
+        |out: &mut String, title: &str| writeln!(out, "\n{title}\n{}", "-".repeat(title.len()));
+
+    writeln!(out, "Swords and Runes morgue file")?;
+    writeln!(out, "============================\n")?;
+    writeln!(
+        out,
+        "{} on depth {} after {} turns.",
+        record.cause_of_death.as_deref().unwrap_or("Died"),
+        record.depth,
+        record.turns
+    )?;
+    writeln!(out, "Score: {}", record.score())?;
+    writeln!(out, "Seed: {}", record.seed)?;
+
+    heading(&mut out, "Attributes")?;
+    writeln!(out, "Strength:     {:>3}", attributes.strength)?;
+    writeln!(out, "Dexterity:    {:>3}", attributes.dexterity)?;
+    writeln!(out, "Intelligence: {:>3}", attributes.intelligence)?;
+    writeln!(out, "Perception:   {:>3}", attributes.perception)?;
+
+    heading(&mut out, "Skills")?;
+    for (name, skill) in skills.iter().sorted_by_key(|&(name, _)| name) {
+        writeln!(out, "{:<13} {:>3}", format!("{name}:"), skill.level())?;
+    }
+
+    heading(&mut out, "Inventory")?;
+    if inventory.is_empty() {
+        writeln!(out, "Nothing at all")?;
+    }
+    for stack in inventory.stacks() {
+        match stack.count {
+            1 => writeln!(out, "{}", item_list[stack.item].name)?,
+            count => writeln!(out, "{count} x {}", item_list[stack.item].name)?,
+        }
+    }
+
+    heading(&mut out, &format!("Kills ({})", record.total_kills()))?;
+    for (name, count) in &record.kills {
+        writeln!(out, "{count:>3} x {name}")?;
+    }
+
+    heading(&mut out, "Last messages")?;
+    for message in messages.last(MORGUE_MESSAGES) {
+        writeln!(out, "{message}")?;
+    }
+
+    Ok(out)
+}
+
+fn load_high_scores(mut commands: Commands, mut messages: ResMut<Messages>) {
+    let high_scores = HighScores::load().unwrap_or_else(|err| {
+        error!("Could not load high scores: {err}");
+        messages.add_hostile(format!("Could not load high scores: {err}"));
+        HighScores::default()
+    });
+
+    commands.insert_resource(high_scores);
+}
+
+/// Write the morgue file and enter the run into the high score table
+fn record_finished_run(
+    record: Res<RunRecord>,
+    player_qry: Query<(&Attributes, &SkillSheet, &Inventory), With<Player>>,
+    item_list: Res<ItemList>,
+    mut high_scores: ResMut<HighScores>,
+    mut messages: ResMut<Messages>,
+) {
+    if let Ok(player) = player_qry.get_single() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+
+        let written = morgue(&record, player, &item_list, &messages)
+            .map_err(|err| err.to_string())
+            .and_then(|morgue| {
+                let path = get_data_path(format!("morgue/morgue-{timestamp}.txt"))
+                    .ok_or("No data directory to save to")?;
+                write_data_file(&path, morgue)?;
+                Ok(path)
+            });
+        match written {
+            Ok(path) => info!("Wrote morgue file to {}", path.display()),
+            Err(err) => {
+                error!("Could not write morgue file: {err}");
+                messages.add_hostile(format!("Could not write morgue file: {err}"));
+            }
+        }
+    }
+
+    high_scores.insert(HighScore::from(&*record));
+    if let Err(err) = high_scores.save() {
+        error!("Could not save high scores: {err}");
+        messages.add_hostile(format!("Could not save high scores: {err}"));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MorguePlugin;
+
+impl Plugin for MorguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_high_scores)
+            .add_systems(OnEnter(GameState::GameOver), record_finished_run);
+    }
+}
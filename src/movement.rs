@@ -1,18 +1,34 @@
 use bevy::prelude::*;
 
 use crate::{
+    animation::AnimationSettings,
     bump::BumpEvent,
-    dungeon::{BlocksMovement, TilePos, TILE_SIZE_F32},
+    dungeon::{BlocksMovement, TilePos},
     input_manager::{Action, Actions, InputManager},
     setup::Player,
     TurnState,
 };
 
+/// Seconds a sprite takes to slide from one tile to the next
+const TWEEN_SECS: f32 = 0.08;
+
+/// Offset of a sprite from the center of its tile, e.g. for decorations scattered about a tile
+#[derive(Debug, Default, Clone, Copy, PartialEq, Component)]
+pub struct SpriteOffset(pub Vec2);
+
+/// Slides an entity's sprite from where it was towards its current [`TilePos`]
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct Tween {
+    from: Vec2,
+    to: Vec2,
+    elapsed: f32,
+}
+
 #[allow(clippy::type_complexity)]
 pub fn movement(
     actions: Res<Actions>,
-    mut player_qry: Query<(Entity, &mut Transform), With<Player>>,
-    blockers_qry: Query<(Entity, &Transform), (With<BlocksMovement>, Without<Player>)>,
+    mut player_qry: Query<(Entity, &mut TilePos), With<Player>>,
+    blockers_qry: Query<(Entity, &TilePos), (With<BlocksMovement>, Without<Player>)>,
     mut next_state: ResMut<NextState<TurnState>>,
     mut bump_events: EventWriter<BumpEvent>,
 ) {
@@ -22,48 +38,46 @@ pub fn movement(
         return;
     }
 
-    let mut delta = Vec2::ZERO;
+    let mut delta = IVec2::ZERO;
 
     if actions.perform(Action::WalkNorth)
         || actions.perform(Action::WalkNortheast)
         || actions.perform(Action::WalkNorthwest)
     {
-        delta += Vec2::Y;
+        delta += IVec2::Y;
     }
     if actions.perform(Action::WalkEast)
         || actions.perform(Action::WalkNortheast)
         || actions.perform(Action::WalkSoutheast)
     {
-        delta += Vec2::X;
+        delta += IVec2::X;
     }
     if actions.perform(Action::WalkSouth)
         || actions.perform(Action::WalkSoutheast)
         || actions.perform(Action::WalkSouthwest)
     {
-        delta += Vec2::NEG_Y;
+        delta += IVec2::NEG_Y;
     }
     if actions.perform(Action::WalkWest)
         || actions.perform(Action::WalkSouthwest)
         || actions.perform(Action::WalkNorthwest)
     {
-        delta += Vec2::NEG_X;
+        delta += IVec2::NEG_X;
     }
 
-    if delta.length_squared() > 0.1 {
-        delta = delta.round() * TILE_SIZE_F32;
-
-        if let Ok((player, mut transform)) = player_qry.get_single_mut() {
-            let dest = TilePos::from(transform.translation.truncate() + delta);
+    if delta != IVec2::ZERO {
+        if let Ok((player, mut pos)) = player_qry.get_single_mut() {
+            let dest = pos.saturating_add_signed(delta);
 
             // If there's nothing in the destination blocking movement, allow the move
             if let Some(blocker) = blockers_qry
                 .iter()
-                .find(|&(_, transform)| TilePos::from(*transform) == dest)
+                .find(|&(_, blocker_pos)| *blocker_pos == dest)
                 .map(|(entity, _)| entity)
             {
                 bump_events.send(BumpEvent::new(player, blocker));
             } else {
-                transform.translation = dest.as_vec().extend(transform.translation.z);
+                *pos = dest;
 
                 // We did our move, end our turn
                 next_state.set(TurnState::MonsterTurn);
@@ -72,6 +86,74 @@ pub fn movement(
     }
 }
 
+/// Put an entity's sprite straight onto its tile rather than sliding it there, e.g. when it's
+/// been teleported across the map
+pub fn snap_to_tile(
+    commands: &mut Commands,
+    entity: Entity,
+    pos: TilePos,
+    transform: &mut Transform,
+) {
+    transform.translation = pos.as_vec().extend(transform.translation.z);
+    commands.entity(entity).remove::<Tween>();
+}
+
+/// Derive sprites' transforms from their [`TilePos`]
+///
+/// [`TilePos`] is the authoritative position of everything in the dungeon; sprites are placed
+/// wherever it says, sliding there if they've moved from one tile to another.
+#[allow(clippy::type_complexity)]
+fn sync_transforms(
+    mut commands: Commands,
+    mut moved_qry: Query<
+        (Entity, Ref<TilePos>, Option<&SpriteOffset>, &mut Transform),
+        Changed<TilePos>,
+    >,
+    settings: Res<AnimationSettings>,
+) {
+    for (entity, pos, offset, mut transform) in moved_qry.iter_mut() {
+        let to = pos.as_vec() + offset.map_or(Vec2::ZERO, |offset| offset.0);
+
+        // Anything snapped into place has already arrived
+        if pos.is_added() || !settings.enabled || transform.translation.truncate() == to {
+            // Newly placed things (and everything, if we don't want animation) just snap into place
+            transform.translation = to.extend(transform.translation.z);
+            commands.entity(entity).remove::<Tween>();
+        } else {
+            commands.entity(entity).insert(Tween {
+                from: transform.translation.truncate(),
+                to,
+                elapsed: 0.0,
+            });
+        }
+    }
+}
+
+/// Slide sprites along their tweens, removing the tween once they arrive
+fn tween_movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tween_qry: Query<(Entity, &mut Tween, &mut Transform)>,
+) {
+    for (entity, mut tween, mut transform) in tween_qry.iter_mut() {
+        tween.elapsed += time.delta_seconds();
+        let progress = (tween.elapsed / TWEEN_SECS).min(1.0);
+
+        transform.translation = tween
+            .from
+            .lerp(tween.to, progress)
+            .extend(transform.translation.z);
+
+        if progress >= 1.0 {
+            commands.entity(entity).remove::<Tween>();
+        }
+    }
+}
+
+/// Label for the systems that move sprites, so that e.g. the camera can follow them
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, SystemSet)]
+pub struct SpriteMovement;
+
 #[derive(Debug, Default)]
 pub struct MovementPlugin;
 
@@ -79,9 +161,14 @@ impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             Update,
-            movement
-                .after(InputManager)
-                .run_if(in_state(TurnState::PlayerTurn)),
+            (
+                movement
+                    .after(InputManager)
+                    .run_if(in_state(TurnState::PlayerTurn)),
+                (sync_transforms, apply_deferred, tween_movement)
+                    .chain()
+                    .in_set(SpriteMovement),
+            ),
         );
     }
 }
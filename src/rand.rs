@@ -17,6 +17,10 @@ impl Random {
         Self(Xoshiro512StarStar::from_entropy())
     }
 
+    pub fn from_seed(&self, seed: u64) -> Self {
+        Self(Xoshiro512StarStar::seed_from_u64(seed))
+    }
+
     pub fn roll_3d6(&mut self) -> i32 {
         self.roll_dice(3)
     }
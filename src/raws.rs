@@ -0,0 +1,372 @@
+//! Game data ("raws") loaded from YAML files through the asset system
+//!
+//! Each raws file is a stream of YAML documents, one per mob, item, etc., which is loaded as-is
+//! and only turned into game data once it's arrived. When the `hot_reload` feature is enabled,
+//! saving a raws file rebuilds the data from it straight away, without restarting the game.
+//!
+//! Raws come in data packs: the game's own, followed by any mods in the `mods` directory. Each pack
+//! can add new mobs and items, or replace ones from packs loaded before it by using the same name.
+//! Spells are written out as part of the items casting them, so they're replaced along with them.
+//!
+//! Everything wrong with a raws file is collected up and reported together, rather than stopping
+//! at the first problem. Running the game with `--check-raws` does just that, without starting
+//! the game itself.
+
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::{AssetLoader, FileAssetIo, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{items::ItemList, mobs::MobList, GameState};
+
+mod packs;
+pub use packs::{data_packs, DataPack, PackManifest};
+
+/// The raws files each data pack can have
+const MOBS_FILE: &str = "mobs.yaml";
+const ITEMS_FILE: &str = "items.yaml";
+
+/// A single raws file
+///
+/// It's kept as text, rather than parsed up front, so any problems with it can be pointed out by
+/// line and column.
+#[derive(Debug, Clone, PartialEq, Eq, TypeUuid, TypePath)]
+#[uuid = "5b3c4a4e-2f57-4b0a-9d37-0d6f1c8e7a21"]
+pub struct Raws {
+    file: String,
+    text: String,
+}
+
+impl Raws {
+    pub fn new(file: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            text: text.into(),
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Find an asset used by something in this file, looking in its own data pack first and then
+    /// falling back on the game's own assets
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        Path::new(&self.file)
+            .parent()
+            .map(|pack_dir| pack_dir.join(path))
+            .filter(|in_pack| asset_exists(in_pack))
+            .unwrap_or_else(|| path.to_owned())
+    }
+
+    /// Deserialize every document in the file, along with its index, collecting the errors from
+    /// any that can't be rather than stopping at the first
+    pub fn parse<T: DeserializeOwned>(&self, errors: &mut Vec<RawsError>) -> Vec<(usize, T)> {
+        serde_yaml::Deserializer::from_str(&self.text)
+            .enumerate()
+            .filter_map(
+                |(document, deserializer)| match T::deserialize(deserializer) {
+                    Ok(data) => Some((document, data)),
+                    Err(err) => {
+                        errors.push(self.error(document, err));
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    /// An error with one of the documents in the file
+    pub fn error(&self, document: usize, message: impl Display) -> RawsError {
+        RawsError {
+            file: self.file.clone(),
+            document,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Something wrong with a document in a raws file
+///
+/// The message starts with the path to the field at fault, where there is one, e.g.
+/// `spell.target: unknown variant...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawsError {
+    pub file: String,
+    /// Index of the document within the file, from 0
+    pub document: usize,
+    pub message: String,
+}
+
+impl Display for RawsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, document {}: {}",
+            self.file,
+            self.document + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for RawsError {}
+
+/// Something looked up by name that isn't in the raws
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotInRaws {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl NotInRaws {
+    pub fn new(kind: &'static str, name: impl Into<String>) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+        }
+    }
+}
+
+impl Display for NotInRaws {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "There's no {} called \"{}\"", self.kind, self.name)
+    }
+}
+
+impl std::error::Error for NotInRaws {}
+
+/// The assets directory, where the asset system loads everything from
+pub fn assets_dir() -> PathBuf {
+    FileAssetIo::get_base_path().join("assets")
+}
+
+/// Whether there's a file at a path within the assets directory
+pub fn asset_exists<P: AsRef<Path>>(path: P) -> bool {
+    assets_dir().join(path).is_file()
+}
+
+/// Check every data pack's raws straight from disk, without going through the asset system
+pub fn check_raws(packs: &[DataPack]) -> Vec<RawsError> {
+    let mut errors = Vec::new();
+    let mut read = |file: &str| {
+        let mut all_raws = Vec::new();
+        for path in packs.iter().filter_map(|pack| pack.file(file)) {
+            match std::fs::read_to_string(assets_dir().join(&path)) {
+                Ok(text) => all_raws.push(Raws::new(path.display().to_string(), text)),
+                Err(err) => errors.push(RawsError {
+                    file: path.display().to_string(),
+                    document: 0,
+                    message: err.to_string(),
+                }),
+            }
+        }
+        all_raws
+    };
+    let (mob_raws, item_raws) = (read(MOBS_FILE), read(ITEMS_FILE));
+
+    errors.extend(MobList::from_raws(&mob_raws).err().into_iter().flatten());
+    errors.extend(ItemList::from_raws(&item_raws).err().into_iter().flatten());
+    errors
+}
+
+#[derive(Debug, Default)]
+struct RawsLoader;
+
+impl AssetLoader for RawsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let raws = Raws::new(
+                load_context.path().display().to_string(),
+                std::str::from_utf8(bytes)?,
+            );
+            load_context.set_default_asset(LoadedAsset::new(raws));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["yaml"]
+    }
+}
+
+/// Handles to everything that has to be loaded before a game can start
+///
+/// Holding on to these keeps the assets loaded for as long as the game runs, rather than them
+/// being dropped and loaded all over again each time something new uses them.
+#[derive(Debug, Default, Clone, Resource)]
+pub struct GameAssets {
+    /// Every data pack's mob raws, in load order
+    mobs: Vec<Handle<Raws>>,
+    /// Every data pack's item raws, in load order
+    items: Vec<Handle<Raws>>,
+    /// Sprites and fonts
+    other: Vec<HandleUntyped>,
+}
+
+fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let (packs, problems) = data_packs();
+    for problem in problems {
+        warn!("{problem}");
+    }
+    info!("Loading {} data pack(s), in order:", packs.len());
+    for (i, pack) in packs.iter().enumerate() {
+        let PackManifest { name, version, .. } = &pack.manifest;
+        info!("  {}. {name} {version} ({})", i + 1, pack.dir.display());
+    }
+
+    // Mods can bring sprites of their own, alongside their raws
+    let mut other = Vec::new();
+    let mod_sprites = packs.iter().skip(1).map(|pack| pack.dir.join("sprites"));
+    for folder in [PathBuf::from("sprites"), PathBuf::from("fonts")]
+        .into_iter()
+        .chain(mod_sprites.filter(|folder| assets_dir().join(folder).is_dir()))
+    {
+        match asset_server.load_folder(&folder) {
+            Ok(handles) => other.extend(handles),
+            Err(err) => error!("Unable to load the {} folder: {err}", folder.display()),
+        }
+    }
+
+    let load_raws = |file| {
+        packs
+            .iter()
+            .filter_map(|pack| pack.file(file))
+            .map(|path| asset_server.load(path))
+            .collect()
+    };
+    commands.insert_resource(GameAssets {
+        mobs: load_raws(MOBS_FILE),
+        items: load_raws(ITEMS_FILE),
+        other,
+    });
+}
+
+/// Put a list built from the raws in place, or report what's wrong with them
+///
+/// A list that's already loaded is kept when there are errors, so mistakes made while editing the
+/// raws don't bring down a running game. There's nothing to fall back on the first time round,
+/// though.
+fn replace_list<T: Resource>(
+    commands: &mut Commands,
+    list: Result<T, Vec<RawsError>>,
+    current: Option<&T>,
+    kind: &str,
+) {
+    match list {
+        Ok(list) => {
+            commands.insert_resource(list);
+            info!("Loaded the {kind} list");
+        }
+        Err(errors) => {
+            let errors = errors
+                .iter()
+                .map(|err| format!("\n  {err}"))
+                .collect::<String>();
+            if current.is_some() {
+                error!("Keeping the current {kind} list, as the raws have errors:{errors}");
+            } else {
+                panic!("Unable to load the {kind} list, as the raws have errors:{errors}");
+            }
+        }
+    }
+}
+
+/// Build the mob and item lists from every data pack's raws, whenever they've all loaded or any
+/// of them have been changed
+fn build_lists(
+    mut commands: Commands,
+    mut raws_evt: EventReader<AssetEvent<Raws>>,
+    raws: Res<Assets<Raws>>,
+    game_assets: Res<GameAssets>,
+    (mob_list, item_list): (Option<Res<MobList>>, Option<Res<ItemList>>),
+) {
+    let (mut mobs_changed, mut items_changed) = (false, false);
+    for event in raws_evt.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            mobs_changed |= game_assets.mobs.contains(handle);
+            items_changed |= game_assets.items.contains(handle);
+        }
+    }
+    // Nothing's built until every pack's raws are in, as later packs can change earlier ones
+    let all_loaded = |handles: &[Handle<Raws>]| {
+        handles
+            .iter()
+            .map(|handle| raws.get(handle))
+            .collect::<Option<Vec<_>>>()
+    };
+
+    if let Some(mob_raws) = all_loaded(&game_assets.mobs).filter(|_| mobs_changed) {
+        let list = MobList::from_raws(mob_raws);
+        replace_list(&mut commands, list, mob_list.as_deref(), "mob");
+    }
+    if let Some(item_raws) = all_loaded(&game_assets.items).filter(|_| items_changed) {
+        let list = ItemList::from_raws(item_raws).map(|list| match item_list.as_deref() {
+            Some(current) => list.keep_ids_from(current),
+            None => list,
+        });
+        replace_list(&mut commands, list, item_list.as_deref(), "item");
+    }
+}
+
+/// Wait for everything to finish loading before setting up the game
+fn check_assets_loaded(
+    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    lists: (Option<Res<MobList>>, Option<Res<ItemList>>),
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // There's no game without the raws, but a missing sprite or font can be lived with
+    for raws in game_assets.mobs.iter().chain(&game_assets.items) {
+        if asset_server.get_load_state(raws) == LoadState::Failed {
+            let path = asset_server
+                .get_handle_path(raws)
+                .map(|path| path.path().display().to_string());
+            panic!("Failed to load raws from {}", path.unwrap_or_default());
+        }
+    }
+    let loading = game_assets.other.iter().any(|handle| {
+        matches!(
+            asset_server.get_load_state(handle),
+            LoadState::NotLoaded | LoadState::Loading
+        )
+    });
+
+    // The lists are built once their raws have finished loading
+    if !loading && lists.0.is_some() && lists.1.is_some() {
+        next_state.set(GameState::Setup);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RawsPlugin;
+
+impl Plugin for RawsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Raws>()
+            .init_asset_loader::<RawsLoader>()
+            .add_systems(Startup, load_assets)
+            .add_systems(
+                Update,
+                (
+                    build_lists,
+                    check_assets_loaded
+                        .after(build_lists)
+                        .run_if(in_state(GameState::AssetsLoading)),
+                ),
+            );
+    }
+}
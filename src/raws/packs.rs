@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use super::{asset_exists, assets_dir};
+
+/// Where the game's own data pack lives, within the assets directory
+const BASE_PACK: &str = "dat";
+/// Where mods are installed, each in a directory of its own within the assets directory
+const MODS_DIR: &str = "mods";
+/// The file describing a data pack, found at the top of its directory
+const MANIFEST: &str = "pack.yaml";
+
+/// What a data pack says about itself
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Mods are loaded from the lowest load order to the highest, with ties going by name
+    ///
+    /// The game's own pack is always loaded first, whatever its load order.
+    #[serde(default)]
+    pub load_order: i32,
+}
+
+/// A directory of raws, either the game's own or a mod's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPack {
+    pub manifest: PackManifest,
+    /// The pack's directory, within the assets directory
+    pub dir: PathBuf,
+}
+
+impl DataPack {
+    fn read<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let manifest_path = assets_dir().join(dir).join(MANIFEST);
+        let manifest = fs::read_to_string(&manifest_path)
+            .map_err(|err| format!("{}: {err}", manifest_path.display()))?;
+        let manifest = serde_yaml::from_str(&manifest)
+            .map_err(|err| format!("{}: {err}", manifest_path.display()))?;
+
+        Ok(Self {
+            manifest,
+            dir: dir.to_owned(),
+        })
+    }
+
+    /// The path to one of the pack's files within the assets directory, if the pack has it
+    pub fn file<P: AsRef<Path>>(&self, file: P) -> Option<PathBuf> {
+        Some(self.dir.join(file)).filter(|path| asset_exists(path))
+    }
+}
+
+/// Every data pack, in the order they're loaded, along with any problems reading them
+///
+/// Mods that can't be read are left out.
+pub fn data_packs() -> (Vec<DataPack>, Vec<String>) {
+    let mut problems = Vec::new();
+    let base = DataPack::read(BASE_PACK).unwrap_or_else(|err| {
+        problems.push(format!("Unable to read the game's own manifest, {err}"));
+        DataPack {
+            manifest: PackManifest {
+                name: "Swords and Runes".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                description: String::new(),
+                load_order: 0,
+            },
+            dir: PathBuf::from(BASE_PACK),
+        }
+    });
+
+    let mut mods: Vec<_> = fs::read_dir(assets_dir().join(MODS_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(
+            |entry| match DataPack::read(Path::new(MODS_DIR).join(entry.file_name())) {
+                Ok(pack) => Some(pack),
+                Err(err) => {
+                    problems.push(format!(
+                        "Skipping a mod, as its manifest can't be read: {err}"
+                    ));
+                    None
+                }
+            },
+        )
+        .collect();
+    mods.sort_by(|a, b| {
+        (a.manifest.load_order, &a.manifest.name).cmp(&(b.manifest.load_order, &b.manifest.name))
+    });
+
+    (std::iter::once(base).chain(mods).collect(), problems)
+}
@@ -0,0 +1,120 @@
+//! A record of how the current run is going, for summing it up once it's over
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    combat::{remove_dead, Died},
+    rand::prelude::*,
+    setup::Player,
+    GameState, TurnState,
+};
+
+/// How the current run is going
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct RunRecord {
+    /// Turns the player has taken
+    pub turns: u32,
+    /// The deepest level of the dungeon the player has reached
+    pub depth: u32,
+    /// Monsters the player has killed, by name
+    pub kills: BTreeMap<String, u32>,
+    /// What killed the player, once they've died
+    pub cause_of_death: Option<String>,
+    /// The seed the dungeon was generated from
+    pub seed: u64,
+}
+
+impl RunRecord {
+    /// How many monsters the player has killed in all
+    pub fn total_kills(&self) -> u32 {
+        self.kills.values().sum()
+    }
+
+    /// Whether the run has come to an end
+    pub fn is_over(&self) -> bool {
+        self.cause_of_death.is_some()
+    }
+
+    /// How well the run went, for the high score table
+    ///
+    /// Getting deeper counts for the most, with a little extra for every kill along the way.
+    pub fn score(&self) -> u32 {
+        self.depth * 100 + self.total_kills() * 10
+    }
+
+    /// The seed for whatever's left to chance during the current turn, e.g. where monsters wander
+    ///
+    /// Like the levels' seeds, it's derived from the run's, so replaying the run plays out the same.
+    pub fn turn_seed(&self) -> u64 {
+        // Spread out so it doesn't land on the seeds the levels count up from the run's
+        self.seed
+            ^ (u64::from(self.depth) << 48)
+            ^ (u64::from(self.turns) << 16)
+            ^ 0x9e37_79b9_7f4a_7c15
+    }
+}
+
+/// A name with an indefinite article in front, e.g. "an Orc"
+fn with_article(name: &str) -> String {
+    let article = if name.starts_with(['A', 'E', 'I', 'O', 'U', 'a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    };
+
+    format!("{article} {name}")
+}
+
+fn start_record(mut commands: Commands, random: Res<Random>) {
+    commands.insert_resource(RunRecord {
+        depth: 1,
+        seed: random.from_entropy().next_u64(),
+        ..Default::default()
+    });
+}
+
+fn count_turn(mut record: ResMut<RunRecord>) {
+    record.turns += 1;
+}
+
+fn record_deaths(
+    mut died_evt: EventReader<Died>,
+    player_qry: Query<(), With<Player>>,
+    mut record: ResMut<RunRecord>,
+) {
+    for died in died_evt.iter() {
+        if player_qry.contains(died.entity) {
+            record.cause_of_death = Some(match &died.killer_name {
+                Some(killer) if died.killer != Some(died.entity) => {
+                    format!("Killed by {}", with_article(killer))
+                }
+                Some(_) => "Killed themselves".to_string(),
+                None => "Died".to_string(),
+            });
+        } else if died
+            .killer
+            .is_some_and(|killer| player_qry.contains(killer))
+        {
+            *record.kills.entry(died.name.clone()).or_default() += 1;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RecordPlugin;
+
+impl Plugin for RecordPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunRecord>()
+            .add_systems(OnEnter(GameState::Setup), start_record)
+            .add_systems(
+                OnEnter(TurnState::MonsterTurn),
+                count_turn.run_if(in_state(GameState::Running)),
+            )
+            // Deaths are recorded as soon as they happen, so the record is complete by the time
+            // the game is over
+            .add_systems(PostUpdate, record_deaths.after(remove_dead));
+    }
+}
@@ -3,13 +3,16 @@ use bevy::prelude::*;
 use crate::{
     camera::PrimaryCamera,
     combat::HP,
-    dungeon::generate_dungeon,
-    fieldofview::HideOutOfSight,
+    dungeon::{generate_dungeon, Map, TilePos},
     inventory::Inventory,
     items::ItemList,
     mobs::MobList,
+    movement::snap_to_tile,
     rand::prelude::*,
-    stats::{Attributes, Skill, SkillSheet},
+    record::RunRecord,
+    spawner::SpawnTable,
+    stats::{Attributes, Experience, Skill, SkillSheet},
+    ui::Messages,
     utils::SpriteLayer,
     GameState, TurnState,
 };
@@ -17,100 +20,91 @@ use crate::{
 #[derive(Debug, Default, Clone, Copy, Component)]
 pub struct Player;
 
-fn setup_game(
-    mut commands: Commands,
-    mut next_state: ResMut<NextState<GameState>>,
-    asset_server: Res<AssetServer>,
-    mut camera: Query<&mut Transform, With<PrimaryCamera>>,
-    mob_list: Res<MobList>,
-    item_list: Res<ItemList>,
-    random: Res<Random>,
-) {
+/// The most times each room rolls on the monster spawn table
+const MAX_MOB_GROUPS_PER_ROOM: u32 = 2;
+/// The most times each room rolls on the item spawn table
+const MAX_ITEM_GROUPS_PER_ROOM: u32 = 2;
+
+/// The seed a level of the dungeon is generated from
+///
+/// Every level gets its own seed, derived from the run's so the whole run can be replayed.
+fn level_seed(record: &RunRecord) -> u64 {
+    record
+        .seed
+        .wrapping_add(2 * record.depth.saturating_sub(1) as u64)
+}
+
+/// Generate the current level of the dungeon along with its monsters and items, returning the
+/// level's map and where the player starts on it
+fn generate_level(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    (mob_list, item_list): (&MobList, &ItemList),
+    random: &Random,
+    record: &RunRecord,
+) -> (Map, TilePos) {
     let width = 80;
     let height = 45;
+    let seed = level_seed(record);
 
     // === Generate Dungeon ===
     let (map, player_start) = generate_dungeon(
         width,
         height,
-        &mut commands,
-        &asset_server,
-        random.from_entropy(),
+        commands,
+        asset_server,
+        random.from_seed(seed),
     );
 
-    // === Center Camera ===
-    if let Ok(mut transform) = camera.get_single_mut() {
-        transform.translation = map.size.center().extend(transform.translation.z);
-    }
-
-    // === Spawn Monsters ===
-    let mut rng = random.from_entropy();
+    // === Spawn Monsters and Items ===
+    // Seeded separately from the dungeon itself, but still reproducible from the run's seed
+    let mut rng = random.from_seed(seed.wrapping_add(1));
+    let mob_table = SpawnTable::for_depth(mob_list.spawn_rules(), record.depth);
+    let item_table = SpawnTable::for_depth(item_list.spawn_rules(), record.depth);
     for room in map.iter_rooms() {
-        let n = rng.gen_range(0..=3);
-        for tile in room.iter().choose_multiple(&mut rng, n) {
-            let entity = if rng.gen_bool(0.2) {
-                mob_list.spawn("Ogre", &mut commands, &asset_server)
-            } else {
-                mob_list.spawn("Orc", &mut commands, &asset_server)
-            };
-            commands
-                .entity(entity)
-                .insert(tile.as_transform(SpriteLayer::Actor));
-        }
-    }
+        let rolls = rng.gen_range(0..=MAX_MOB_GROUPS_PER_ROOM);
+        mob_table.populate(rolls, room.iter(), &mut rng, |name, tile, tiles, rng| {
+            if let Err(err) = mob_list.spawn_group(name, tile, tiles, rng, commands, asset_server) {
+                error!("Unable to spawn a group of monsters: {err}");
+            }
+        });
 
-    // === Spawn Potions ===
-    let potion = item_list.get("Potion of Healing");
-    for room in map.iter_rooms() {
-        let n = rng.gen_range(0..=5).clamp(3, 5) - 3; // 0-3 = 0; 4-5 = 1-2
-        for tile in room.iter().choose_multiple(&mut rng, n) {
-            commands.spawn((
-                SpriteBundle {
-                    texture: asset_server.load("sprites/items/potions/brilliant_blue.png"),
-                    transform: tile.as_transform(SpriteLayer::Item),
-                    ..Default::default()
-                },
-                Name::new(item_list[potion].name.clone()),
-                HideOutOfSight::Hide,
-                potion,
-            ));
-        }
+        // Items can lie underneath monsters, so they get the whole room to themselves
+        let rolls = rng.gen_range(0..=MAX_ITEM_GROUPS_PER_ROOM);
+        item_table.populate(rolls, room.iter(), &mut rng, |item, tile, _, _| {
+            item_list.spawn(item, tile, commands, asset_server);
+        });
     }
 
-    // === Spawn Scrolls ===
-    let scroll = item_list.get("Scroll of Lightning");
-    for room in map.iter_rooms() {
-        let n = rng.gen_range(0..=5).clamp(3, 5) - 3; // 0-3 = 0; 4-5 = 1-2
-        for tile in room.iter().choose_multiple(&mut rng, n) {
-            commands.spawn((
-                SpriteBundle {
-                    texture: asset_server.load("sprites/items/scrolls/scroll-purple.png"),
-                    transform: tile.as_transform(SpriteLayer::Item),
-                    ..Default::default()
-                },
-                Name::new(item_list[scroll].name.clone()),
-                HideOutOfSight::Hide,
-                scroll,
-            ));
-        }
-    }
+    (map, player_start)
+}
 
-    let scroll = item_list.get("Scroll of Fireball");
-    for room in map.iter_rooms() {
-        let n = rng.gen_range(3..=5).clamp(3, 5) - 3; // 0-3 = 0; 4-5 = 1-2
-        for tile in room.iter().choose_multiple(&mut rng, n) {
-            commands.spawn((
-                SpriteBundle {
-                    texture: asset_server.load("sprites/items/scrolls/scroll-red.png"),
-                    transform: tile.as_transform(SpriteLayer::Item),
-                    ..Default::default()
-                },
-                Name::new(item_list[scroll].name.clone()),
-                HideOutOfSight::Hide,
-                scroll,
-            ));
-        }
+/// Point the camera at the middle of the map
+fn center_camera(camera: &mut Query<&mut Transform, With<PrimaryCamera>>, map: &Map) {
+    if let Ok(mut transform) = camera.get_single_mut() {
+        transform.translation = map.size.center().extend(transform.translation.z);
     }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_game(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+    mut camera: Query<&mut Transform, With<PrimaryCamera>>,
+    mob_list: Res<MobList>,
+    item_list: Res<ItemList>,
+    random: Res<Random>,
+    record: Res<RunRecord>,
+) {
+    let (map, player_start) = generate_level(
+        &mut commands,
+        &asset_server,
+        (&mob_list, &item_list),
+        &random,
+        &record,
+    );
+    center_camera(&mut camera, &map);
 
     // === Spawn Player ===
     let mut skills = SkillSheet::new();
@@ -122,6 +116,7 @@ fn setup_game(
             transform: player_start.as_transform(SpriteLayer::Actor),
             ..Default::default()
         },
+        player_start,
         HP::new(30),
         skills,
         Name::new("Player"),
@@ -131,6 +126,7 @@ fn setup_game(
             intelligence: 12,
             perception: 12,
         },
+        Experience::default(),
         Inventory::new(25),
         Player,
     ));
@@ -140,9 +136,62 @@ fn setup_game(
     next_state.set(GameState::Running);
 }
 
-fn load_raws(mut commands: Commands) {
-    commands.insert_resource(MobList::from_raws());
-    commands.insert_resource(ItemList::from_raws());
+/// Generate the next level down, bringing the player along to it
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn setup_next_level(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+    mut camera: Query<&mut Transform, With<PrimaryCamera>>,
+    mut player_qry: Query<
+        (Entity, &mut TilePos, &mut Transform),
+        (With<Player>, Without<PrimaryCamera>),
+    >,
+    mob_list: Res<MobList>,
+    item_list: Res<ItemList>,
+    random: Res<Random>,
+    record: Res<RunRecord>,
+) {
+    let (map, player_start) = generate_level(
+        &mut commands,
+        &asset_server,
+        (&mob_list, &item_list),
+        &random,
+        &record,
+    );
+    center_camera(&mut camera, &map);
+
+    if let Ok((player, mut pos, mut transform)) = player_qry.get_single_mut() {
+        *pos = player_start;
+        snap_to_tile(&mut commands, player, player_start, &mut transform);
+    }
+
+    commands.insert_resource(map);
+    next_state.set(GameState::Running);
+}
+
+/// Clear away whatever is left of a previous game
+///
+/// Everything that's part of the dungeon has a [`TilePos`], so that's all that needs to go.
+fn clear_dungeon(mut commands: Commands, dungeon_qry: Query<Entity, With<TilePos>>) {
+    for entity in dungeon_qry.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Clear away the level the player is leaving, taking nothing but the player with them
+fn clear_level(
+    mut commands: Commands,
+    level_qry: Query<Entity, (With<TilePos>, Without<Player>)>,
+    mut record: ResMut<RunRecord>,
+    mut messages: ResMut<Messages>,
+) {
+    for entity in level_qry.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    record.depth += 1;
+    messages.add_notice(format!("You descend to depth {}.", record.depth));
 }
 
 /// Ensure the game starts ready for the player to choose their first action
@@ -155,8 +204,13 @@ pub struct SetupPlugin;
 
 impl Plugin for SetupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_raws)
+        app.add_systems(OnEnter(GameState::Setup), clear_dungeon)
             .add_systems(Update, setup_game.run_if(in_state(GameState::Setup)))
-            .add_systems(OnExit(GameState::Setup), start_turn);
+            .add_systems(OnExit(GameState::Setup), start_turn)
+            .add_systems(OnEnter(GameState::Descending), clear_level)
+            .add_systems(
+                Update,
+                setup_next_level.run_if(in_state(GameState::Descending)),
+            );
     }
 }
@@ -0,0 +1,141 @@
+//! Weighted spawn tables for filling the dungeon with monsters and items
+//!
+//! Every mob and item in the raws lists the depths it can turn up at, how likely it is to be picked
+//! there compared to everything else, and how many of it turn up together. Anything without any
+//! spawn rules is never placed in the dungeon, but can still be given out some other way.
+
+use serde::Deserialize;
+
+use crate::{dungeon::TilePos, rand::prelude::*};
+
+/// How many of something turn up together, written in the raws as either a single number or a
+/// `[min, max]` range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum GroupSize {
+    Exactly(u32),
+    Between(u32, u32),
+}
+
+impl Default for GroupSize {
+    fn default() -> Self {
+        Self::Exactly(1)
+    }
+}
+
+impl GroupSize {
+    pub fn roll(&self, rng: &mut Random) -> u32 {
+        match *self {
+            GroupSize::Exactly(size) => size,
+            GroupSize::Between(min, max) => rng.gen_range(min..=max.max(min)),
+        }
+    }
+}
+
+/// When, and how often, something is spawned in the dungeon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnRule {
+    /// The shallowest depth this applies at
+    #[serde(default = "default_min_depth")]
+    pub min_depth: u32,
+    /// The deepest depth this applies at, if there is one
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    /// How likely this is to be picked, relative to everything else at the same depth
+    pub weight: u32,
+    #[serde(default, alias = "group")]
+    pub group_size: GroupSize,
+}
+
+fn default_min_depth() -> u32 {
+    1
+}
+
+impl SpawnRule {
+    pub fn applies_at(&self, depth: u32) -> bool {
+        depth >= self.min_depth && self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    /// Check for mistakes that still make for a valid rule, but one that can never apply as meant
+    ///
+    /// The problem is described starting with the field it's in.
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.max_depth, self.group_size) {
+            (Some(max_depth), _) if max_depth < self.min_depth => Err(format!(
+                "max_depth: {max_depth} is shallower than min_depth {}",
+                self.min_depth
+            )),
+            (_, GroupSize::Between(min, max)) if max < min => Err(format!(
+                "group_size: the largest group ({max}) is smaller than the smallest ({min})"
+            )),
+            _ if self.weight == 0 => Err("weight: must be more than 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Everything that can be spawned at a particular depth, along with how likely each is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnTable<T> {
+    entries: Vec<(T, SpawnRule)>,
+}
+
+impl<T: Copy + Ord> SpawnTable<T> {
+    /// Build the table for a depth from everything's spawn rules
+    ///
+    /// Where more than one of something's rules applies, the first one wins.
+    pub fn for_depth<'a>(
+        rules: impl IntoIterator<Item = (T, &'a [SpawnRule])>,
+        depth: u32,
+    ) -> Self {
+        let mut entries: Vec<_> = rules
+            .into_iter()
+            .filter_map(|(what, rules)| {
+                rules
+                    .iter()
+                    .find(|rule| rule.applies_at(depth) && rule.weight > 0)
+                    .map(|&rule| (what, rule))
+            })
+            .collect();
+        // The raws don't come in any particular order, so sort them to keep a seed reproducible
+        entries.sort_unstable_by_key(|&(what, _)| what);
+
+        Self { entries }
+    }
+
+    /// Pick something to spawn, and how many of it
+    pub fn roll(&self, rng: &mut Random) -> Option<(T, u32)> {
+        self.entries
+            .choose_weighted(rng, |(_, rule)| rule.weight)
+            .ok()
+            .map(|(what, rule)| (*what, rule.group_size.roll(rng)))
+    }
+
+    /// Roll on the table a number of times, handing each pick a free tile of its own along with
+    /// the rest of the room's free tiles, for anything it brings with it
+    ///
+    /// Groups are cut short if the room runs out of tiles.
+    pub fn populate(
+        &self,
+        rolls: u32,
+        tiles: impl IntoIterator<Item = TilePos>,
+        rng: &mut Random,
+        mut spawn: impl FnMut(T, TilePos, &mut Vec<TilePos>, &mut Random),
+    ) {
+        let mut tiles: Vec<_> = tiles.into_iter().collect();
+        tiles.shuffle(rng);
+
+        for _ in 0..rolls {
+            let Some((what, count)) = self.roll(rng) else {
+                return;
+            };
+            for _ in 0..count {
+                let Some(tile) = tiles.pop() else {
+                    return;
+                };
+                spawn(what, tile, &mut tiles, rng);
+            }
+        }
+    }
+}
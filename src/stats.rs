@@ -7,7 +7,7 @@ pub mod skills;
 pub use skills::{Skill, SkillSheet};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Component)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct Attributes {
     pub strength: u8,
     pub dexterity: u8,
@@ -26,7 +26,43 @@ impl Default for Attributes {
     }
 }
 
+/// Experience gained from killing monsters, and the level it's worth
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Experience(u32);
+
+impl Experience {
+    pub fn xp(&self) -> u32 {
+        self.0
+    }
+
+    /// The experience needed to reach a level, with each level needing more than the last
+    pub fn needed_for(level: u32) -> u32 {
+        10 * level * level.saturating_sub(1)
+    }
+
+    pub fn level(&self) -> u32 {
+        (1..)
+            .find(|&level| self.0 < Self::needed_for(level + 1))
+            .unwrap()
+    }
+
+    /// Gain some experience, returning whether it was enough to go up a level
+    pub fn gain(&mut self, xp: u32) -> bool {
+        let level = self.level();
+        self.0 = self.0.saturating_add(xp);
+
+        self.level() > level
+    }
+}
+
 impl Attributes {
+    /// The least and most damage a hit can do
+    pub fn damage_range(&self) -> (i32, i32) {
+        let mean = self.strength as f64 / 2.0;
+
+        ((0.5 * mean).round() as i32, (1.5 * mean).round() as i32)
+    }
+
     pub fn roll_damage(&self, rng: &mut Random) -> i32 {
         let mean = self.strength as f64 / 2.0;
         let std_dev = 0.25;
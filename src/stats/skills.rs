@@ -16,12 +16,28 @@ impl Skill {
         self.0
     }
 
+    /// The level a check is made against, after modifiers
+    pub fn effective_level(&self, modifiers: i32) -> i32 {
+        (self.0 + modifiers).clamp(0, 20)
+    }
+
     pub fn check(&self, modifiers: i32, rng: &mut Random) -> (bool, i32) {
         let roll = rng.roll_3d6();
-        let effective_level = (self.0 + modifiers).clamp(0, 20);
+        let effective_level = self.effective_level(modifiers);
 
         (roll <= effective_level, effective_level - roll)
     }
+
+    /// The chance of passing a check, i.e. of rolling the effective level or under on 3d6
+    pub fn success_chance(&self, modifiers: i32) -> f32 {
+        let effective_level = self.effective_level(modifiers);
+        let successes = (1..=6)
+            .flat_map(|a| (1..=6).flat_map(move |b| (1..=6).map(move |c| a + b + c)))
+            .filter(|&roll| roll <= effective_level)
+            .count();
+
+        successes as f32 / 216.0
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
@@ -39,4 +55,9 @@ impl SkillSheet {
     pub fn set<S: Into<String>>(&mut self, skill: S, level: Skill) {
         self.0.insert(skill.into(), level);
     }
+
+    /// Every skill on the sheet, by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Skill)> {
+        self.0.iter().map(|(name, &skill)| (name.as_str(), skill))
+    }
 }
@@ -0,0 +1,584 @@
+//! Travelling to a distant tile, one turn at a time
+//!
+//! The player picks a destination by clicking an explored tile or with the targeting cursor, sets
+//! off exploring, or runs in a direction, and walks over the terrain they know about. Travel stops
+//! as soon as anything interesting happens: a hostile or item comes into view, the player gets
+//! hurt, or they press a key.
+
+use std::collections::{HashSet, VecDeque};
+
+use bevy::{
+    ecs::{query::Has, system::SystemParam},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use itertools::Itertools;
+
+use crate::{
+    animation::animations_finished,
+    camera::PrimaryCamera,
+    combat::HP,
+    dungeon::{BlocksMovement, Map, Tile, TilePos},
+    fieldofview::FieldOfView,
+    input_manager::{Action, Actions, InputManager},
+    inventory::{Autopickup, Dropped},
+    items::ItemId,
+    mobs::Mob,
+    movement::Tween,
+    setup::Player,
+    ui::{GameUi, Messages, TargetConfirmed},
+    GameState, TurnState,
+};
+
+/// Where the player is headed
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Journey {
+    /// Walking a path to a chosen destination; the tiles left to walk, nearest first
+    Path(VecDeque<TilePos>),
+    /// Heading for whatever is nearest and not yet explored
+    Explore,
+    /// Running in a direction until something interesting turns up
+    Run { direction: IVec2, along: RunAlong },
+}
+
+/// How a run decides where to go next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunAlong {
+    /// Following a corridor around its bends, stopping at junctions
+    Corridor,
+    /// Straight ahead through the open, stopping once the tiles to either side change, e.g. at a
+    /// doorway
+    Open { sides: [bool; 2] },
+}
+
+/// The run actions, and the direction each one runs in
+const RUN_DIRECTIONS: [(Action, IVec2); 8] = [
+    (Action::RunNorth, IVec2::new(0, 1)),
+    (Action::RunEast, IVec2::new(1, 0)),
+    (Action::RunSouth, IVec2::new(0, -1)),
+    (Action::RunWest, IVec2::new(-1, 0)),
+    (Action::RunNortheast, IVec2::new(1, 1)),
+    (Action::RunSoutheast, IVec2::new(1, -1)),
+    (Action::RunSouthwest, IVec2::new(-1, -1)),
+    (Action::RunNorthwest, IVec2::new(-1, 1)),
+];
+
+/// Where the player is travelling to, and what they've already seen along the way
+#[derive(Debug, Default, Resource)]
+pub struct Travel {
+    journey: Option<Journey>,
+    /// The player's HP as of the last step
+    last_hp: u16,
+    /// Hostiles and items that were already in view as of the last step
+    seen: HashSet<Entity>,
+    /// Items the player has already walked over while exploring, e.g. with a full inventory
+    passed_items: HashSet<Entity>,
+}
+
+impl Travel {
+    /// Whether the player is currently travelling anywhere
+    pub fn is_travelling(&self) -> bool {
+        self.journey.is_some()
+    }
+
+    /// Stop travelling
+    pub fn stop(&mut self) {
+        self.journey = None;
+        self.seen.clear();
+        self.passed_items.clear();
+    }
+}
+
+fn stop_travel(mut travel: ResMut<Travel>) {
+    travel.stop();
+}
+
+/// Run condition that is true while the player is travelling
+pub fn is_travelling(travel: Res<Travel>) -> bool {
+    travel.is_travelling()
+}
+
+/// What the player knows about the dungeon, for planning a route through it
+#[derive(SystemParam)]
+pub struct KnownTerrain<'w, 's> {
+    map: Res<'w, Map>,
+    #[allow(clippy::type_complexity)]
+    tile_qry: Query<
+        'w,
+        's,
+        (&'static TilePos, &'static FieldOfView, Has<BlocksMovement>),
+        (With<Tile>, Without<Player>),
+    >,
+}
+
+impl<'w, 's> KnownTerrain<'w, 's> {
+    /// Explored tiles that can be walked on
+    pub fn walkable(&self) -> HashSet<TilePos> {
+        self.tile_qry
+            .iter()
+            .filter(|&(_, &fov, blocked)| fov != FieldOfView::Unexplored && !blocked)
+            .map(|(&pos, _, _)| pos)
+            .collect()
+    }
+
+    /// The shortest path between two tiles over explored terrain, excluding the starting tile
+    pub fn path(&self, from: TilePos, to: TilePos) -> Option<Vec<TilePos>> {
+        let walkable = self.walkable();
+        if !walkable.contains(&to) {
+            return None;
+        }
+
+        pathfinding::directed::astar::astar(
+            &from,
+            |tile| {
+                self.map
+                    .neighbors_of(*tile)
+                    .into_iter()
+                    .filter(|tile| walkable.contains(tile))
+                    .map(|tile| (tile, 1))
+            },
+            |tile| tile.distance(to),
+            |tile| *tile == to,
+        )
+        .map(|(path, _)| path.into_iter().skip(1).collect())
+    }
+
+    /// The shortest path to the nearest unexplored part of the map or one of the given tiles,
+    /// excluding the starting tile
+    pub fn explore_path(&self, from: TilePos, visit: &HashSet<TilePos>) -> Option<Vec<TilePos>> {
+        let walkable = self.walkable();
+        let unexplored: HashSet<_> = self
+            .tile_qry
+            .iter()
+            .filter(|&(_, &fov, _)| fov == FieldOfView::Unexplored)
+            .map(|(&pos, _, _)| pos)
+            .collect();
+
+        pathfinding::directed::bfs::bfs(
+            &from,
+            |tile| {
+                self.map
+                    .neighbors_of(*tile)
+                    .into_iter()
+                    .filter(|tile| walkable.contains(tile))
+            },
+            |tile| {
+                *tile != from
+                    && (visit.contains(tile)
+                        || self
+                            .map
+                            .neighbors_of(*tile)
+                            .iter()
+                            .any(|neighbor| unexplored.contains(neighbor)))
+            },
+        )
+        .map(|path| path.into_iter().skip(1).collect())
+    }
+
+    /// How to run from a tile in a direction, if there's anywhere to run to at all
+    fn run_along(&self, from: TilePos, direction: IVec2) -> Option<RunAlong> {
+        let walkable = self.walkable();
+        if !from
+            .checked_add_signed(direction)
+            .is_some_and(|ahead| walkable.contains(&ahead))
+        {
+            return None;
+        }
+
+        if self.corridor_step(&walkable, from, direction).is_some() {
+            Some(RunAlong::Corridor)
+        } else {
+            Some(RunAlong::Open {
+                sides: open_sides(&walkable, from, direction),
+            })
+        }
+    }
+
+    /// The next tile to run to, if the run should carry on
+    fn run_step(&self, from: TilePos, direction: IVec2, along: RunAlong) -> Option<TilePos> {
+        let walkable = self.walkable();
+        match along {
+            RunAlong::Corridor => self.corridor_step(&walkable, from, direction),
+            RunAlong::Open { sides } => from
+                .checked_add_signed(direction)
+                .filter(|ahead| walkable.contains(ahead))
+                .filter(|_| open_sides(&walkable, from, direction) == sides),
+        }
+    }
+
+    /// The one way onward along a corridor, if there is exactly one
+    fn corridor_step(
+        &self,
+        walkable: &HashSet<TilePos>,
+        from: TilePos,
+        direction: IVec2,
+    ) -> Option<TilePos> {
+        let behind = from.checked_add_signed(-direction);
+        let ahead = from.checked_add_signed(direction);
+
+        let open = self
+            .map
+            .neighbors_of(from)
+            .into_iter()
+            .filter(|tile| walkable.contains(tile) && Some(*tile) != behind)
+            .collect_vec();
+        // Anything next to where we came from could've been reached directly, so isn't onward
+        let onward = open
+            .iter()
+            .copied()
+            .filter(|&tile| behind.is_none_or(|behind| behind.distance(tile) > 1))
+            .collect_vec();
+
+        // Nothing new ahead means the corridor bends back on itself
+        single_passage(if onward.is_empty() { &open } else { &onward }, ahead)
+    }
+}
+
+/// Whether the tiles to either side of a tile are walkable, relative to a direction
+fn open_sides(walkable: &HashSet<TilePos>, at: TilePos, direction: IVec2) -> [bool; 2] {
+    let side = IVec2::new(-direction.y, direction.x);
+    [side, -side].map(|side| {
+        at.checked_add_signed(side)
+            .is_some_and(|tile| walkable.contains(&tile))
+    })
+}
+
+/// Pick the way onward from some candidate tiles, provided they're all part of one passage
+///
+/// Diagonal movement means a bend in a corridor can offer a couple of neighboring tiles; straight
+/// ahead is preferred when it's one of them.
+fn single_passage(candidates: &[TilePos], ahead: Option<TilePos>) -> Option<TilePos> {
+    let &first = candidates.first()?;
+    if candidates
+        .iter()
+        .tuple_combinations()
+        .any(|(a, b)| a.distance(*b) > 1)
+    {
+        // Passages lead off in different directions; we're at a junction
+        return None;
+    }
+
+    Some(
+        ahead
+            .filter(|ahead| candidates.contains(ahead))
+            .unwrap_or(first),
+    )
+}
+
+/// Hostiles and items the player can currently see
+#[derive(SystemParam)]
+pub struct InView<'w, 's> {
+    map: Res<'w, Map>,
+    fov_qry: Query<'w, 's, &'static FieldOfView>,
+    #[allow(clippy::type_complexity)]
+    noteworthy_qry: Query<
+        'w,
+        's,
+        (Entity, &'static TilePos, &'static Name, Has<Mob>),
+        (Or<(With<Mob>, With<ItemId>)>, Without<Player>),
+    >,
+}
+
+/// A hostile or item in view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Noteworthy<'a> {
+    pub entity: Entity,
+    pub pos: TilePos,
+    pub name: &'a Name,
+    pub is_hostile: bool,
+}
+
+impl<'w, 's> InView<'w, 's> {
+    /// Every visible hostile or item
+    pub fn noteworthy(&self) -> impl Iterator<Item = Noteworthy<'_>> {
+        self.noteworthy_qry
+            .iter()
+            .filter(|&(_, &pos, _, _)| {
+                self.map
+                    .get(pos)
+                    .and_then(|tile| self.fov_qry.get(tile).ok())
+                    .is_some_and(|fov| *fov == FieldOfView::Visible)
+            })
+            .map(|(entity, &pos, name, is_hostile)| Noteworthy {
+                entity,
+                pos,
+                name,
+                is_hostile,
+            })
+    }
+}
+
+/// Set off on a journey, taking note of what's already in view so it doesn't interrupt us
+fn begin_travel(travel: &mut Travel, journey: Journey, hp: &HP, in_view: &InView) {
+    travel.stop();
+    travel.journey = Some(journey);
+    travel.last_hp = hp.current();
+    travel.seen = in_view.noteworthy().map(|seen| seen.entity).collect();
+}
+
+/// Plan a route to a destination and start travelling along it
+fn travel_to(
+    destination: TilePos,
+    travel: &mut Travel,
+    terrain: &KnownTerrain,
+    in_view: &InView,
+    player_qry: &Query<(&TilePos, &HP), With<Player>>,
+    messages: &mut Messages,
+) {
+    let Ok((&player_pos, hp)) = player_qry.get_single() else {
+        return;
+    };
+
+    if destination == player_pos {
+        return;
+    }
+
+    match terrain.path(player_pos, destination) {
+        Some(path) => begin_travel(travel, Journey::Path(path.into()), hp, in_view),
+        None => messages.add("You don't know how to get there."),
+    }
+}
+
+/// Travel to the explored tile the player clicks on
+#[allow(clippy::too_many_arguments)]
+fn click_to_travel(
+    buttons: Res<Input<MouseButton>>,
+    window_qry: Query<&Window, With<PrimaryWindow>>,
+    camera_qry: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
+    mut travel: ResMut<Travel>,
+    terrain: KnownTerrain,
+    in_view: InView,
+    player_qry: Query<(&TilePos, &HP), With<Player>>,
+    mut messages: ResMut<Messages>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(cursor) = window_qry
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    let (camera, camera_transform) = camera_qry.single();
+    if let Some(destination) = camera
+        .viewport_to_world_2d(camera_transform, cursor)
+        .and_then(TilePos::from_world)
+    {
+        travel_to(
+            destination,
+            &mut travel,
+            &terrain,
+            &in_view,
+            &player_qry,
+            &mut messages,
+        );
+    }
+}
+
+/// Travel to the tile picked with the targeting cursor
+fn travel_to_target(
+    mut confirm_evt: EventReader<TargetConfirmed>,
+    mut travel: ResMut<Travel>,
+    terrain: KnownTerrain,
+    in_view: InView,
+    player_qry: Query<(&TilePos, &HP), With<Player>>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+    mut messages: ResMut<Messages>,
+) {
+    if let Some(&TargetConfirmed(destination)) = confirm_evt.iter().last() {
+        travel_to(
+            destination,
+            &mut travel,
+            &terrain,
+            &in_view,
+            &player_qry,
+            &mut messages,
+        );
+        ui_state.set(GameUi::Main);
+    }
+}
+
+/// Start exploring, unless there's danger about
+fn auto_explore(
+    actions: Res<Actions>,
+    mut travel: ResMut<Travel>,
+    in_view: InView,
+    player_qry: Query<&HP, With<Player>>,
+    mut messages: ResMut<Messages>,
+) {
+    if !actions.perform(Action::AutoExplore) {
+        return;
+    }
+    let Ok(hp) = player_qry.get_single() else {
+        return;
+    };
+
+    if let Some(hostile) = in_view.noteworthy().find(|seen| seen.is_hostile) {
+        messages.add(format!("Not with {} in view.", hostile.name));
+        return;
+    }
+
+    begin_travel(&mut travel, Journey::Explore, hp, &in_view);
+}
+
+/// Start running in a direction
+fn start_running(
+    actions: Res<Actions>,
+    mut travel: ResMut<Travel>,
+    terrain: KnownTerrain,
+    in_view: InView,
+    player_qry: Query<(&TilePos, &HP), With<Player>>,
+) {
+    let Some(direction) = RUN_DIRECTIONS
+        .into_iter()
+        .find(|&(action, _)| actions.perform(action))
+        .map(|(_, direction)| direction)
+    else {
+        return;
+    };
+    let Ok((&pos, hp)) = player_qry.get_single() else {
+        return;
+    };
+
+    if let Some(along) = terrain.run_along(pos, direction) {
+        begin_travel(&mut travel, Journey::Run { direction, along }, hp, &in_view);
+    }
+}
+
+/// Take the next step towards the destination, unless something interrupts us
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn follow_travel_path(
+    actions: Res<Actions>,
+    mut travel: ResMut<Travel>,
+    terrain: KnownTerrain,
+    in_view: InView,
+    mut player_qry: Query<(&mut TilePos, &HP, Has<Tween>), With<Player>>,
+    blockers_qry: Query<&TilePos, (With<BlocksMovement>, Without<Player>)>,
+    (items_qry, autopickup): (Query<(&ItemId, Has<Dropped>)>, Res<Autopickup>),
+    mut next_state: ResMut<NextState<TurnState>>,
+    mut messages: ResMut<Messages>,
+) {
+    // Any key press takes control back from travel
+    if actions.any() {
+        travel.stop();
+        return;
+    }
+
+    let Ok((mut pos, hp, sliding)) = player_qry.get_single_mut() else {
+        travel.stop();
+        return;
+    };
+
+    // Let the player's sprite arrive before moving on, so travel can be followed on screen
+    if sliding {
+        return;
+    }
+
+    if hp.current() < travel.last_hp {
+        messages.add_hostile("You stop travelling as you're hurt.");
+        travel.stop();
+        return;
+    }
+    travel.last_hp = hp.current();
+
+    // Exploring goes and fetches items rather than stopping for them
+    let exploring = travel.journey == Some(Journey::Explore);
+    let in_view: Vec<_> = in_view.noteworthy().collect();
+    if let Some(seen) = in_view
+        .iter()
+        .find(|seen| (seen.is_hostile || !exploring) && !travel.seen.contains(&seen.entity))
+    {
+        messages.add_notice(format!("{} comes into view.", seen.name));
+        travel.stop();
+        return;
+    }
+    travel.seen = in_view.iter().map(|seen| seen.entity).collect();
+
+    let next = match &mut travel.journey {
+        Some(Journey::Path(path)) => path.pop_front(),
+        Some(Journey::Explore) => {
+            // Only the items that'll be picked up on the way are worth fetching
+            let items: HashSet<_> = in_view
+                .iter()
+                .filter(|seen| !seen.is_hostile && !travel.passed_items.contains(&seen.entity))
+                .filter(|seen| {
+                    items_qry
+                        .get(seen.entity)
+                        .is_ok_and(|(&item_id, dropped)| !dropped && autopickup.is_enabled(item_id))
+                })
+                .map(|seen| seen.pos)
+                .collect();
+            let next = terrain
+                .explore_path(*pos, &items)
+                .and_then(|path| path.first().copied());
+            if next.is_none() {
+                messages.add("There's nothing left to explore.");
+            }
+            next
+        }
+        Some(Journey::Run { direction, along }) => {
+            let next = terrain.run_step(*pos, *direction, *along);
+            if let Some(next) = next {
+                // Corridors may turn, so keep heading whichever way we last went
+                *direction = next - *pos;
+            }
+            next
+        }
+        None => None,
+    };
+    let Some(next) = next else {
+        travel.stop();
+        return;
+    };
+
+    if blockers_qry.iter().any(|&blocker| blocker == next) {
+        messages.add("Something is in the way.");
+        travel.stop();
+        return;
+    }
+
+    travel.passed_items.extend(
+        in_view
+            .iter()
+            .filter(|seen| !seen.is_hostile && seen.pos == next)
+            .map(|seen| seen.entity),
+    );
+    if travel.journey == Some(Journey::Path(VecDeque::new())) {
+        // That's the last step, we've arrived
+        travel.stop();
+    }
+
+    *pos = next;
+    next_state.set(TurnState::MonsterTurn);
+}
+
+#[derive(Debug, Default)]
+pub struct TravelPlugin;
+
+impl Plugin for TravelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Travel>()
+            .add_systems(OnExit(GameState::Running), stop_travel)
+            .add_systems(
+                Update,
+                (
+                    // Travel picks up from the next frame, once the key that started it is released
+                    follow_travel_path
+                        .run_if(in_state(GameUi::Main))
+                        .run_if(is_travelling)
+                        .run_if(animations_finished),
+                    click_to_travel.run_if(in_state(GameUi::Main)),
+                    auto_explore.run_if(in_state(GameUi::Main)),
+                    start_running.run_if(in_state(GameUi::Main)),
+                    travel_to_target.run_if(in_state(GameUi::Travel)),
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(TurnState::WaitingForPlayer))
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
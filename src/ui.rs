@@ -1,15 +1,30 @@
 use bevy::prelude::*;
 
+mod character_sheet_ui;
+mod console_ui;
 mod dungeon_ui;
+mod examine_ui;
+mod game_over_ui;
+mod health_bars;
 mod inventory_ui;
+mod keybindings_ui;
+mod main_menu_ui;
+mod menu;
+mod message_history_ui;
 pub mod messages;
+mod pick_up_ui;
 mod spell_target_ui;
+mod targeting;
+mod throw_target_ui;
 
 pub use inventory_ui::RedrawInventoryUi;
 pub use messages::Messages;
+pub use targeting::{TargetConfirmed, Targeting};
 
 use crate::{
-    input_manager::{Action, Actions},
+    dungeon::Map,
+    input_manager::{Action, Actions, InputManager, KeyMap},
+    record::RunRecord,
     GameState, TurnState,
 };
 
@@ -18,7 +33,31 @@ pub enum GameUi {
     #[default]
     Main,
     Inventory,
+    PickUp,
     TargetSpell,
+    TargetThrow,
+    Look,
+    Travel,
+    Keybindings,
+    MessageHistory,
+    CharacterSheet,
+    Console,
+}
+
+/// Run condition for UIs that use the targeting cursor
+fn is_targeting(ui_state: Res<State<GameUi>>) -> bool {
+    matches!(
+        **ui_state,
+        GameUi::TargetSpell | GameUi::TargetThrow | GameUi::Look | GameUi::Travel
+    )
+}
+
+/// The first key bound to an action, for hints about what to press
+fn first_key_label(keymap: &KeyMap, action: Action) -> String {
+    keymap
+        .bindings(action)
+        .first()
+        .map_or_else(|| "(unbound)".to_string(), ToString::to_string)
 }
 
 fn set_initial_ui_state(mut ui_state: ResMut<NextState<GameUi>>) {
@@ -29,13 +68,54 @@ fn ui_state_manager(
     actions: Res<Actions>,
     current_state: Res<State<GameUi>>,
     mut next_state: ResMut<NextState<GameUi>>,
+    mut game_state: ResMut<NextState<GameState>>,
 ) {
+    if matches!(**current_state, GameUi::Keybindings | GameUi::Console) {
+        // Every key means something else while rebinding or typing, so those screens handle
+        // their own input
+        return;
+    }
+
     if actions.perform(Action::OpenInventory) {
         if *current_state == GameUi::Main {
             next_state.set(GameUi::Inventory);
         } else {
             next_state.set(GameUi::Main);
         }
+    } else if actions.perform(Action::Look) {
+        if *current_state == GameUi::Main {
+            next_state.set(GameUi::Look);
+        } else {
+            next_state.set(GameUi::Main);
+        }
+    } else if actions.perform(Action::Travel) {
+        if *current_state == GameUi::Main {
+            next_state.set(GameUi::Travel);
+        } else {
+            next_state.set(GameUi::Main);
+        }
+    } else if actions.perform(Action::MessageHistory) {
+        if *current_state == GameUi::Main {
+            next_state.set(GameUi::MessageHistory);
+        } else {
+            next_state.set(GameUi::Main);
+        }
+    } else if actions.perform(Action::CharacterSheet) {
+        if *current_state == GameUi::Main {
+            next_state.set(GameUi::CharacterSheet);
+        } else {
+            next_state.set(GameUi::Main);
+        }
+    } else if actions.perform(Action::ToggleConsole) {
+        next_state.set(GameUi::Console);
+    } else if actions.perform(Action::OpenKeybindings) {
+        next_state.set(GameUi::Keybindings);
+    } else if actions.perform(Action::Cancel) {
+        if *current_state == GameUi::Main {
+            game_state.set(GameState::MainMenu);
+        } else {
+            next_state.set(GameUi::Main);
+        }
     }
 }
 
@@ -43,6 +123,18 @@ fn reset_ui(mut ui_state: ResMut<NextState<GameUi>>) {
     ui_state.set(GameUi::Main);
 }
 
+fn clear_messages(mut messages: ResMut<Messages>) {
+    *messages = Messages::new();
+}
+
+/// Messages from here on belong to the turn the player is about to take
+fn set_message_turn(record: Res<RunRecord>, mut messages: ResMut<Messages>) {
+    // Not a change anyone needs to redraw for
+    messages
+        .bypass_change_detection()
+        .set_turn(record.turns + 1);
+}
+
 fn destroy_ui<C: Component>(mut commands: Commands, inventory_ui_qry: Query<Entity, With<C>>) {
     for ui in inventory_ui_qry.iter() {
         commands.entity(ui).despawn_recursive();
@@ -55,9 +147,56 @@ pub struct UIPlugin;
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Messages>()
+            .init_resource::<console_ui::Console>()
+            .init_resource::<throw_target_ui::ItemToThrow>()
             .add_state::<GameUi>()
             .add_event::<RedrawInventoryUi>()
+            // === Main Menu ===
+            .add_systems(OnEnter(GameState::MainMenu), menu::init_menu_selection)
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                (
+                    destroy_ui::<main_menu_ui::MainMenuUi>,
+                    menu::remove_menu_selection,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    main_menu_ui::main_menu_input.run_if(in_state(GameUi::Main)),
+                    main_menu_ui::build_main_menu,
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(GameState::MainMenu)),
+            )
+            // === Game Over ===
+            .add_systems(OnEnter(GameState::GameOver), menu::init_menu_selection)
+            .add_systems(
+                OnExit(GameState::GameOver),
+                (
+                    destroy_ui::<game_over_ui::GameOverUi>,
+                    menu::remove_menu_selection,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    game_over_ui::game_over_input,
+                    game_over_ui::build_game_over_ui,
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(GameState::GameOver)),
+            )
             // === Main Game UI ===
+            // Only clear out messages once there's been a game, so e.g. keymap problems found
+            // at startup aren't lost
+            .add_systems(
+                OnEnter(GameState::Setup),
+                clear_messages.run_if(resource_exists::<Map>()),
+            )
+            .add_systems(OnEnter(TurnState::WaitingForPlayer), set_message_turn)
             .add_systems(OnExit(TurnState::WaitingForPlayer), reset_ui)
             .add_systems(
                 OnEnter(GameState::Running),
@@ -67,9 +206,10 @@ impl Plugin for UIPlugin {
             .add_systems(
                 Update,
                 (
-                    ui_state_manager,
+                    ui_state_manager.after(InputManager),
                     dungeon_ui::update_hp,
                     dungeon_ui::update_message_log,
+                    health_bars::update_health_bars,
                     dungeon_ui::update_looking_at.run_if(in_state(GameUi::Main)),
                 )
                     .run_if(in_state(GameState::Running)),
@@ -84,27 +224,202 @@ impl Plugin for UIPlugin {
                 Update,
                 (
                     inventory_ui::inventory_interaction,
+                    inventory_ui::inventory_actions
+                        .after(InputManager)
+                        .run_if(in_state(TurnState::WaitingForPlayer)),
                     inventory_ui::build_inventory_ui,
                 )
                     .run_if(in_state(GameUi::Inventory)),
             )
-            // == Spell Target UI ==
+            // == Pick Up UI ==
+            .add_systems(OnEnter(GameUi::PickUp), menu::init_menu_selection)
+            .add_systems(
+                OnExit(GameUi::PickUp),
+                (
+                    destroy_ui::<pick_up_ui::PickUpUi>,
+                    menu::remove_menu_selection,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    pick_up_ui::pick_up_input.run_if(in_state(TurnState::WaitingForPlayer)),
+                    pick_up_ui::build_pick_up_ui,
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(GameUi::PickUp)),
+            )
+            // == Character Sheet UI ==
+            .add_systems(
+                OnEnter(GameUi::CharacterSheet),
+                character_sheet_ui::spawn_character_sheet_ui,
+            )
+            .add_systems(
+                OnExit(GameUi::CharacterSheet),
+                destroy_ui::<character_sheet_ui::CharacterSheetUi>,
+            )
+            // == Console UI ==
+            .add_systems(OnEnter(GameUi::Console), console_ui::open_console)
+            .add_systems(OnExit(GameUi::Console), destroy_ui::<console_ui::ConsoleUi>)
+            .add_systems(
+                Update,
+                (console_ui::console_input, console_ui::build_console_ui)
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(GameUi::Console)),
+            )
+            // == Keybindings UI ==
+            .add_systems(
+                OnEnter(GameUi::Keybindings),
+                keybindings_ui::init_keybindings_menu,
+            )
+            .add_systems(
+                OnExit(GameUi::Keybindings),
+                (
+                    destroy_ui::<keybindings_ui::KeybindingsUi>,
+                    keybindings_ui::remove_keybindings_menu,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    keybindings_ui::keybindings_input,
+                    keybindings_ui::build_keybindings_ui,
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(GameUi::Keybindings)),
+            )
+            // == Message History UI ==
+            .add_systems(
+                OnEnter(GameUi::MessageHistory),
+                message_history_ui::init_message_history,
+            )
+            .add_systems(
+                OnExit(GameUi::MessageHistory),
+                (
+                    destroy_ui::<message_history_ui::MessageHistoryUi>,
+                    message_history_ui::remove_message_history,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    message_history_ui::message_history_input,
+                    message_history_ui::build_message_history_ui,
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(in_state(GameUi::MessageHistory)),
+            )
+            // == Targeting Cursor ==
+            .add_event::<TargetConfirmed>()
             .add_systems(
                 OnEnter(GameUi::TargetSpell),
-                spell_target_ui::init_spell_targeting,
+                (
+                    targeting::init_target_cursor,
+                    apply_deferred,
+                    spell_target_ui::init_spell_targeting,
+                    targeting::target_nearest_hostile,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnEnter(GameUi::TargetThrow),
+                (
+                    targeting::init_target_cursor,
+                    apply_deferred,
+                    throw_target_ui::init_throw_targeting,
+                    targeting::target_nearest_hostile,
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(GameUi::Look), targeting::init_target_cursor)
+            .add_systems(OnEnter(GameUi::Travel), targeting::init_target_cursor)
+            .add_systems(
+                OnExit(GameUi::TargetSpell),
+                (
+                    destroy_ui::<targeting::TargetCursor>,
+                    targeting::remove_target_cursor,
+                ),
+            )
+            .add_systems(
+                OnExit(GameUi::TargetThrow),
+                (
+                    destroy_ui::<targeting::TargetCursor>,
+                    targeting::remove_target_cursor,
+                ),
+            )
+            .add_systems(
+                OnExit(GameUi::Look),
+                (
+                    destroy_ui::<targeting::TargetCursor>,
+                    targeting::remove_target_cursor,
+                ),
+            )
+            .add_systems(
+                OnExit(GameUi::Travel),
+                (
+                    destroy_ui::<targeting::TargetCursor>,
+                    targeting::remove_target_cursor,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    targeting::move_target_cursor,
+                    targeting::cycle_targets,
+                    targeting::update_target_cursor,
+                    targeting::confirm_target,
+                )
+                    .chain()
+                    .after(InputManager)
+                    .run_if(is_targeting),
             )
+            // == Look UI ==
+            .add_systems(OnExit(GameUi::Look), destroy_ui::<examine_ui::ExamineUi>)
+            .add_systems(
+                Update,
+                (
+                    examine_ui::build_examine_ui.after(targeting::update_target_cursor),
+                    targeting::finish_looking.after(targeting::confirm_target),
+                )
+                    .run_if(in_state(GameUi::Look)),
+            )
+            // == Spell Target UI ==
             .add_systems(
                 OnExit(GameUi::TargetSpell),
-                destroy_ui::<spell_target_ui::SpellTargetUi>,
+                (
+                    destroy_ui::<spell_target_ui::SpellTargetUi>,
+                    spell_target_ui::clear_spell_to_cast,
+                ),
             )
             .add_systems(
                 Update,
                 (
-                    spell_target_ui::update_single_target_select,
                     spell_target_ui::update_area_target_select,
-                    spell_target_ui::fire_area_target_spell,
+                    spell_target_ui::fire_spell,
                 )
+                    .after(targeting::confirm_target)
                     .run_if(in_state(GameUi::TargetSpell)),
+            )
+            // == Throw Target UI ==
+            .add_systems(
+                OnExit(GameUi::TargetThrow),
+                (
+                    destroy_ui::<throw_target_ui::ThrowTargetUi>,
+                    throw_target_ui::clear_item_to_throw,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    spell_target_ui::update_area_target_select,
+                    throw_target_ui::throw_item,
+                )
+                    .after(targeting::confirm_target)
+                    .run_if(in_state(GameUi::TargetThrow)),
             );
     }
 }
@@ -0,0 +1,194 @@
+use bevy::{ecs::query::Has, prelude::*};
+use itertools::Itertools;
+
+use crate::{
+    combat::{GodMode, HP},
+    inventory::Inventory,
+    record::RunRecord,
+    setup::Player,
+    stats::{Attributes, Experience, SkillSheet},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct CharacterSheetUi;
+
+/// How someone is holding up, going by how many hit points they have left
+pub(super) fn condition(hp: &HP) -> &'static str {
+    match hp.ratio() {
+        ratio if ratio >= 1.0 => "Unhurt",
+        ratio if ratio >= 0.75 => "Scratched",
+        ratio if ratio >= 0.5 => "Wounded",
+        ratio if ratio >= 0.25 => "Badly wounded",
+        _ => "Near death",
+    }
+}
+
+/// The lasting effects on someone, by name
+///
+/// Potions and spells all take effect straight away, so only god mode lingers for now.
+pub(super) fn effects(god_mode: bool) -> Vec<&'static str> {
+    let mut effects = Vec::new();
+    if god_mode {
+        effects.push("God mode");
+    }
+
+    effects
+}
+
+/// The sections of the sheet, each a title and its lines
+fn sections(
+    (attributes, skills, hp, inventory, experience, god_mode): (
+        &Attributes,
+        &SkillSheet,
+        &HP,
+        &Inventory,
+        &Experience,
+        bool,
+    ),
+    record: &RunRecord,
+) -> Vec<(&'static str, Vec<String>)> {
+    let (min_damage, max_damage) = attributes.damage_range();
+
+    vec![
+        (
+            "Attributes",
+            vec![
+                format!("Strength      {:>3}", attributes.strength),
+                format!("Dexterity     {:>3}", attributes.dexterity),
+                format!("Intelligence  {:>3}", attributes.intelligence),
+                format!("Perception    {:>3}", attributes.perception),
+            ],
+        ),
+        (
+            "Skills",
+            skills
+                .iter()
+                .sorted_by_key(|&(name, _)| name)
+                .map(|(name, skill)| {
+                    format!(
+                        "{name:<13} {:>3}  {:>5.1}% on 3d6",
+                        skill.effective_level(0),
+                        skill.success_chance(0) * 100.0
+                    )
+                })
+                .collect(),
+        ),
+        (
+            "Derived",
+            vec![
+                format!("Hit points    {}/{}", hp.current(), hp.max()),
+                format!("Damage        {min_damage}-{max_damage}"),
+                format!(
+                    "Carrying      {}/{} items",
+                    inventory.size(),
+                    inventory.capacity()
+                ),
+            ],
+        ),
+        ("Status", vec![condition(hp).to_string()]),
+        (
+            "Effects",
+            match effects(god_mode).as_slice() {
+                [] => vec!["None".to_string()],
+                effects => effects.iter().map(ToString::to_string).collect(),
+            },
+        ),
+        // Weapons and armour can be carried around, but not yet equipped
+        ("Equipment", vec!["Nothing equipped".to_string()]),
+        (
+            "Progress",
+            vec![
+                format!("Level         {:>3}", experience.level()),
+                format!(
+                    "Experience    {}/{}",
+                    experience.xp(),
+                    Experience::needed_for(experience.level() + 1)
+                ),
+                format!("Depth         {:>3}", record.depth),
+                format!("Turns         {:>3}", record.turns),
+                format!("Kills         {:>3}", record.total_kills()),
+                format!("Score         {:>3}", record.score()),
+            ],
+        ),
+    ]
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn spawn_character_sheet_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    player_qry: Query<
+        (
+            &Attributes,
+            &SkillSheet,
+            &HP,
+            &Inventory,
+            &Experience,
+            Has<GodMode>,
+        ),
+        With<Player>,
+    >,
+    record: Res<RunRecord>,
+) {
+    let Ok(player) = player_qry.get_single() else {
+        return;
+    };
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font_handle.clone(),
+        font_size,
+        color,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            CharacterSheetUi,
+        ))
+        .with_children(|container| {
+            container
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        width: Val::Px(480.0),
+                        padding: UiRect::all(Val::Px(15.0)),
+                        ..Default::default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                    ..Default::default()
+                })
+                .with_children(|sheet| {
+                    sheet.spawn(TextBundle::from_section(
+                        "Character",
+                        text_style(32.0, Color::WHITE),
+                    ));
+
+                    for (title, lines) in sections(player, &record) {
+                        sheet.spawn(
+                            TextBundle::from_section(title, text_style(24.0, Color::YELLOW))
+                                .with_style(Style {
+                                    margin: UiRect::top(Val::Px(10.0)),
+                                    ..Default::default()
+                                }),
+                        );
+                        for line in lines {
+                            sheet.spawn(TextBundle::from_section(
+                                line,
+                                text_style(20.0, Color::WHITE),
+                            ));
+                        }
+                    }
+                });
+        });
+}
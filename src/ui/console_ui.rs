@@ -0,0 +1,202 @@
+use bevy::{prelude::*, window::ReceivedCharacter};
+
+use crate::{
+    console::{ConsoleCommand, COMMANDS},
+    input_manager::{Action, Actions},
+    items::ItemList,
+    mobs::MobList,
+};
+
+use super::GameUi;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct ConsoleUi;
+
+/// State of the wizard console, kept between uses so earlier commands can be brought back up
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub(super) struct Console {
+    /// The line being typed
+    input: String,
+    /// Commands entered so far, oldest first
+    history: Vec<String>,
+    /// Index into the history of the command brought back up, if any
+    browsing: Option<usize>,
+    /// What the last attempt at completing the line could have meant
+    suggestions: Vec<String>,
+}
+
+impl Console {
+    /// Complete the command or argument being typed as far as it can unambiguously go
+    fn complete(&mut self, mob_list: &MobList, item_list: &ItemList) {
+        let (kept, partial, candidates): (String, &str, Vec<&str>) =
+            match self.input.split_once(' ') {
+                None => (String::new(), &self.input, COMMANDS.to_vec()),
+                Some((command, arg)) => (
+                    format!("{command} "),
+                    arg,
+                    match command.to_lowercase().as_str() {
+                        "spawn" => mob_list.names().collect(),
+                        "give" => item_list.names().collect(),
+                        _ => Vec::new(),
+                    },
+                ),
+            };
+
+        let partial = partial.to_lowercase();
+        let mut matches: Vec<_> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&partial))
+            .collect();
+        matches.sort_unstable();
+
+        match matches[..] {
+            [] => {}
+            [only] => {
+                self.input = format!("{kept}{only}");
+                self.suggestions.clear();
+            }
+            [first, ..] => {
+                let common = first
+                    .char_indices()
+                    .map(|(idx, c)| idx + c.len_utf8())
+                    .take_while(|&end| {
+                        matches.iter().all(|other| {
+                            other
+                                .get(..end)
+                                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&first[..end]))
+                        })
+                    })
+                    .last()
+                    .unwrap_or(0);
+                if common > partial.len() {
+                    self.input = format!("{kept}{}", &first[..common]);
+                }
+                self.suggestions = matches.iter().map(|name| name.to_string()).collect();
+            }
+        }
+    }
+
+    /// Bring back an earlier (or later) command from the history
+    fn browse(&mut self, older: bool) {
+        let browsing = match (self.browsing, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (Some(idx), true) => Some(idx.saturating_sub(1)),
+            (Some(idx), false) if idx + 1 < self.history.len() => Some(idx + 1),
+            (_, false) => None,
+        };
+
+        self.browsing = browsing;
+        self.input = browsing
+            .map(|idx| self.history[idx].clone())
+            .unwrap_or_default();
+    }
+}
+
+/// Start with an empty line, ignoring whatever was typed to open the console
+pub(super) fn open_console(
+    mut console: ResMut<Console>,
+    mut chars: ResMut<Events<ReceivedCharacter>>,
+) {
+    console.input.clear();
+    console.browsing = None;
+    console.suggestions.clear();
+    chars.clear();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn console_input(
+    actions: Res<Actions>,
+    keys: Res<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut console: ResMut<Console>,
+    mob_list: Res<MobList>,
+    item_list: Res<ItemList>,
+    mut command_evt: EventWriter<ConsoleCommand>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+) {
+    if actions.perform(Action::ToggleConsole) || keys.just_pressed(KeyCode::Escape) {
+        ui_state.set(GameUi::Main);
+        return;
+    }
+
+    for typed in chars.iter().filter(|typed| !typed.char.is_control()) {
+        console.input.push(typed.char);
+        console.suggestions.clear();
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+        console.suggestions.clear();
+    } else if keys.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter]) {
+        let line = std::mem::take(&mut console.input).trim().to_string();
+        console.browsing = None;
+        console.suggestions.clear();
+
+        if !line.is_empty() {
+            if console.history.last() != Some(&line) {
+                console.history.push(line.clone());
+            }
+            command_evt.send(ConsoleCommand(line));
+        }
+    } else if keys.just_pressed(KeyCode::Tab) {
+        console.complete(&mob_list, &item_list);
+    } else if keys.just_pressed(KeyCode::Up) {
+        console.browse(true);
+    } else if keys.just_pressed(KeyCode::Down) {
+        console.browse(false);
+    }
+}
+
+pub(super) fn build_console_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    console: Res<Console>,
+    console_ui_qry: Query<Entity, With<ConsoleUi>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in console_ui_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_style = |color: Color| TextStyle {
+        font: font_handle.clone(),
+        font_size: 24.0,
+        color,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..Default::default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                // Shown over the top of the rest of the game's UI
+                z_index: ZIndex::Global(1),
+                ..Default::default()
+            },
+            ConsoleUi,
+        ))
+        .with_children(|panel| {
+            if !console.suggestions.is_empty() {
+                panel.spawn(TextBundle::from_section(
+                    console.suggestions.join("  "),
+                    text_style(Color::GRAY),
+                ));
+            }
+
+            panel.spawn(TextBundle::from_section(
+                format!("> {}_", console.input),
+                text_style(Color::WHITE),
+            ));
+        });
+}
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
 use crate::{
     camera::PrimaryCamera,
@@ -152,10 +152,17 @@ pub(super) fn spawn_dungeon_ui(
 }
 
 pub(super) fn update_hp(
-    player_hp_qry: Query<&HP, (Changed<HP>, With<Player>)>,
+    player_hp_qry: Query<Ref<HP>, With<Player>>,
+    new_hp_bar_qry: Query<(), Added<HPBar>>,
     mut hp_bar_qry: Query<(Option<&mut Style>, Option<&mut Text>), With<HPBar>>,
 ) {
-    if let Ok(player_hp) = player_hp_qry.get_single() {
+    if let Ok(hp) = player_hp_qry.get_single() {
+        // The bar needs filling in whenever it's been (re)built, too
+        if !hp.is_changed() && new_hp_bar_qry.is_empty() {
+            return;
+        }
+        let player_hp: &HP = &hp;
+
         for (style, text) in hp_bar_qry.iter_mut() {
             if let Some(mut style) = style {
                 style.width = Val::Percent(player_hp.percent());
@@ -190,59 +197,81 @@ pub(super) fn update_message_log(
     }
 }
 
+/// Lists the names of everything the player can make out on a given tile
+#[derive(SystemParam)]
+pub(super) struct TileDescriber<'w, 's> {
+    map: Res<'w, Map>,
+    tile_qry: Query<'w, 's, (&'static Name, &'static FieldOfView), With<Tile>>,
+    names_qry: Query<'w, 's, (&'static Name, &'static TilePos), Without<Tile>>,
+}
+
+impl<'w, 's> TileDescriber<'w, 's> {
+    pub(super) fn names_at(&self, tile: TilePos) -> Vec<&Name> {
+        let mut names = Vec::new();
+
+        if let Some((tile_name, fov)) = self
+            .map
+            .get(tile)
+            .and_then(|tile_entity| self.tile_qry.get(tile_entity).ok())
+        {
+            if *fov != FieldOfView::Unexplored {
+                names.push(tile_name);
+            }
+
+            if *fov == FieldOfView::Visible {
+                names.extend(self.names_qry.iter().filter_map(|(name, &pos)| {
+                    if pos == tile {
+                        Some(name)
+                    } else {
+                        None
+                    }
+                }));
+            }
+        }
+
+        names
+    }
+}
+
+/// Replace the contents of the "looking at" panel with the given names
+pub(super) fn set_looking_at(text: &mut Text, names: Vec<&Name>, font: Handle<Font>) {
+    text.sections = names
+        .into_iter()
+        .map(|name| {
+            let mut name = name.as_str().to_owned();
+            name.push('\n');
+            TextSection {
+                value: name,
+                style: TextStyle {
+                    font: font.clone(),
+                    font_size: 32.0,
+                    color: Color::WHITE,
+                },
+            }
+        })
+        .collect();
+}
+
 pub(super) fn update_looking_at(
     mut cursor_evt: EventReader<CursorMoved>,
     mut ui_text_qry: Query<&mut Text, With<LookingAt>>,
     camera_qry: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
-    tile_qry: Query<(&Name, &FieldOfView), With<Tile>>,
-    names_qry: Query<(&Name, &Transform), Without<Tile>>,
-    map: Res<Map>,
+    describer: TileDescriber,
     asset_server: Res<AssetServer>,
 ) {
     if let Some(cursor) = cursor_evt.iter().last() {
         let (camera, camera_transform) = camera_qry.single();
         if let Some(world_position) = camera.viewport_to_world_2d(camera_transform, cursor.position)
         {
-            let tile = TilePos::from(world_position);
-            let mut names = Vec::new();
+            let names = TilePos::from_world(world_position)
+                .map(|tile| describer.names_at(tile))
+                .unwrap_or_default();
 
-            if let Some((tile_name, fov)) = map
-                .get(tile)
-                .and_then(|tile_entity| tile_qry.get(tile_entity).ok())
-            {
-                if *fov != FieldOfView::Unexplored {
-                    names.push(tile_name);
-                }
-
-                if *fov == FieldOfView::Visible {
-                    names.extend(names_qry.iter().filter_map(|(name, transform)| {
-                        let pos = TilePos::from(transform);
-                        if pos == tile {
-                            Some(name)
-                        } else {
-                            None
-                        }
-                    }));
-                }
-            }
-
-            let mut text = ui_text_qry.single_mut();
-            let font_handle = asset_server.load("fonts/FiraMono-Medium.ttf");
-            text.sections = names
-                .into_iter()
-                .map(|name| {
-                    let mut name = name.as_str().to_owned();
-                    name.push('\n');
-                    TextSection {
-                        value: name,
-                        style: TextStyle {
-                            font: font_handle.clone(),
-                            font_size: 32.0,
-                            color: Color::WHITE,
-                        },
-                    }
-                })
-                .collect();
+            set_looking_at(
+                &mut ui_text_qry.single_mut(),
+                names,
+                asset_server.load("fonts/FiraMono-Medium.ttf"),
+            );
         }
     }
 }
@@ -0,0 +1,171 @@
+//! A closer look at a monster or item under the targeting cursor
+
+use bevy::{ecs::query::Has, prelude::*};
+
+use crate::{
+    combat::{expected_damage, GodMode, HP},
+    dungeon::{Map, Tile, TilePos},
+    fieldofview::FieldOfView,
+    items::ItemId,
+    mobs::{Description, Mob, Wields},
+    setup::Player,
+    stats::{Attributes, SkillSheet},
+};
+
+use super::{
+    character_sheet_ui::{condition, effects},
+    Targeting,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct ExamineUi;
+
+/// A rough idea of how a fight would go, going by how many turns each side would need to finish
+/// off the other
+fn threat(
+    (player_hp, player_skills, player_attributes): (&HP, &SkillSheet, &Attributes),
+    (mob_hp, mob_skills, mob_attributes): (&HP, &SkillSheet, &Attributes),
+) -> (&'static str, Color) {
+    let turns_to_kill = |hp: &HP, damage: f32| {
+        if damage > 0.0 {
+            hp.current() as f32 / damage
+        } else {
+            f32::INFINITY
+        }
+    };
+    let player_needs = turns_to_kill(
+        mob_hp,
+        expected_damage((player_skills, player_attributes), mob_skills),
+    );
+    let mob_needs = turns_to_kill(
+        player_hp,
+        expected_damage((mob_skills, mob_attributes), player_skills),
+    );
+
+    // How many times over the player could win the fight before losing it
+    let margin = mob_needs / player_needs;
+    match margin {
+        margin if margin.is_nan() => ("looks about as tough as you", Color::WHITE),
+        margin if margin >= 4.0 => ("looks like an easy fight", Color::GREEN),
+        margin if margin >= 1.5 => ("looks weaker than you", Color::GREEN),
+        margin if margin >= 0.67 => ("looks about as tough as you", Color::WHITE),
+        margin if margin >= 0.25 => ("looks tougher than you", Color::ORANGE),
+        _ => ("looks deadly", Color::RED),
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(super) fn build_examine_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    targeting: Res<Targeting>,
+    map: Res<Map>,
+    tile_qry: Query<&FieldOfView, With<Tile>>,
+    player_qry: Query<(&HP, &SkillSheet, &Attributes), With<Player>>,
+    mob_qry: Query<
+        (
+            &Name,
+            &TilePos,
+            &HP,
+            &SkillSheet,
+            &Attributes,
+            Option<&Description>,
+            Option<&Wields>,
+            Has<GodMode>,
+        ),
+        (With<Mob>, Without<Player>),
+    >,
+    item_qry: Query<(&Name, &TilePos, Option<&Description>), With<ItemId>>,
+    examine_ui_qry: Query<Entity, With<ExamineUi>>,
+) {
+    if !targeting.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in examine_ui_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    // Only what the player can see right now can be examined
+    let in_view = map
+        .get(targeting.cursor)
+        .and_then(|tile| tile_qry.get(tile).ok())
+        .is_some_and(|&fov| fov == FieldOfView::Visible);
+    if !in_view {
+        return;
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font_handle.clone(),
+        font_size,
+        color,
+    };
+
+    // A monster stands on top of anything lying on the floor, so it's what gets examined first
+    let mob = mob_qry
+        .iter()
+        .find(|&(_, &pos, ..)| pos == targeting.cursor);
+    let (name, description, mut lines) = match (mob, player_qry.get_single()) {
+        (
+            Some((name, _, mob_hp, mob_skills, mob_attributes, description, wields, god_mode)),
+            Ok(player),
+        ) => {
+            let (threat, threat_color) = threat(player, (mob_hp, mob_skills, mob_attributes));
+            let effects = match effects(god_mode).as_slice() {
+                [] => "none".to_string(),
+                effects => effects.join(", "),
+            };
+            let wields = wields.map_or("nothing", |Wields(wields)| wields.as_str());
+            (
+                name,
+                description,
+                vec![
+                    (format!("It {threat}."), threat_color),
+                    (format!("Condition: {}", condition(mob_hp)), Color::WHITE),
+                    (format!("Effects: {effects}"), Color::WHITE),
+                    (format!("Wielding: {wields}"), Color::WHITE),
+                ],
+            )
+        }
+        _ => match item_qry
+            .iter()
+            .find(|&(_, &pos, _)| pos == targeting.cursor)
+        {
+            Some((name, _, description)) => (name, description, Vec::new()),
+            None => return,
+        },
+    };
+    if let Some(Description(description)) = description {
+        lines.insert(0, (description.clone(), Color::GRAY));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    width: Val::Px(400.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                ..Default::default()
+            },
+            ExamineUi,
+        ))
+        .with_children(|panel| {
+            panel.spawn(TextBundle::from_section(
+                name.as_str(),
+                text_style(32.0, Color::WHITE),
+            ));
+
+            for (line, color) in lines {
+                panel.spawn(TextBundle::from_section(line, text_style(20.0, color)));
+            }
+        });
+}
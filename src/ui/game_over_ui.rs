@@ -0,0 +1,112 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{input_manager::Actions, record::RunRecord, GameState};
+
+use super::menu::{menu_backdrop, navigate_menu, spawn_menu_entries, MenuEntry, MenuSelection};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct GameOverUi;
+
+/// The choices on the game over screen, in the order they're shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GameOverEntry {
+    StartAgain,
+    Quit,
+}
+
+impl GameOverEntry {
+    const ALL: [GameOverEntry; 2] = [Self::StartAgain, Self::Quit];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::StartAgain => "Start Again",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+/// Sum up the run that just ended
+fn summary(record: &RunRecord) -> Vec<String> {
+    let mut lines = vec![
+        record
+            .cause_of_death
+            .clone()
+            .unwrap_or_else(|| "Died".to_string()),
+        format!("Depth reached: {}", record.depth),
+        format!("Turns taken: {}", record.turns),
+        format!("Monsters killed: {}", record.total_kills()),
+    ];
+    lines.extend(
+        record
+            .kills
+            .iter()
+            .map(|(name, count)| format!("  {count} x {name}")),
+    );
+
+    lines
+}
+
+pub(super) fn build_game_over_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selection: Res<MenuSelection>,
+    record: Res<RunRecord>,
+    game_over_qry: Query<Entity, With<GameOverUi>>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in game_over_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let entries: Vec<_> = GameOverEntry::ALL
+        .iter()
+        .map(|entry| (entry.label(), true))
+        .collect();
+
+    commands
+        .spawn((menu_backdrop(), GameOverUi))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                "You Died",
+                TextStyle {
+                    font: font_handle.clone(),
+                    font_size: 64.0,
+                    color: Color::RED,
+                },
+            ));
+
+            for line in summary(&record) {
+                menu.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font: font_handle.clone(),
+                        font_size: 24.0,
+                        color: Color::GRAY,
+                    },
+                ));
+            }
+
+            spawn_menu_entries(menu, &entries, *selection, font_handle.clone());
+        });
+}
+
+pub(super) fn game_over_input(
+    actions: Res<Actions>,
+    entry_qry: Query<(&Interaction, &MenuEntry), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    match navigate_menu(&actions, &entry_qry, &mut selection, &[true; 2])
+        .map(|idx| GameOverEntry::ALL[idx])
+    {
+        Some(GameOverEntry::StartAgain) => game_state.set(GameState::AssetsLoading),
+        Some(GameOverEntry::Quit) => exit.send(AppExit),
+        None => {}
+    }
+}
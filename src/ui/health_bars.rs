@@ -0,0 +1,97 @@
+//! Small health bars over monsters that have been hurt
+
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{combat::HP, dungeon::TILE_SIZE_F32, mobs::Mob};
+
+const BAR_WIDTH: f32 = TILE_SIZE_F32 - 4.0;
+const BAR_HEIGHT: f32 = 3.0;
+
+/// Marker component for the background of a monster's health bar
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct HealthBar;
+
+/// Marker component for the part of a health bar showing the hit points left
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct HealthBarFill;
+
+/// The fill color of a health bar, going from green to red as hit points run out
+fn fill_color(hp: &HP) -> Color {
+    match hp.ratio() {
+        ratio if ratio > 0.5 => Color::GREEN,
+        ratio if ratio > 0.25 => Color::YELLOW,
+        _ => Color::RED,
+    }
+}
+
+fn fill_sprite(hp: &HP) -> Sprite {
+    Sprite {
+        color: fill_color(hp),
+        custom_size: Some(Vec2::new(BAR_WIDTH * hp.ratio(), BAR_HEIGHT)),
+        anchor: Anchor::CenterLeft,
+        ..Default::default()
+    }
+}
+
+/// Add, update, or remove health bars as monsters' hit points change
+///
+/// The bars are children of the monsters, so they're hidden along with them when out of sight.
+#[allow(clippy::type_complexity)]
+pub(super) fn update_health_bars(
+    mut commands: Commands,
+    hurt_qry: Query<(Entity, &HP, Option<&Children>), (With<Mob>, Changed<HP>)>,
+    bar_qry: Query<&Children, With<HealthBar>>,
+    mut fill_qry: Query<&mut Sprite, With<HealthBarFill>>,
+) {
+    for (mob, hp, children) in hurt_qry.iter() {
+        let bar = children
+            .into_iter()
+            .flatten()
+            .find(|&&child| bar_qry.contains(child))
+            .copied();
+
+        match bar {
+            Some(bar) if hp.current() == hp.max() => commands.entity(bar).despawn_recursive(),
+            Some(bar) => {
+                for &fill in bar_qry.get(bar).into_iter().flatten() {
+                    if let Ok(mut sprite) = fill_qry.get_mut(fill) {
+                        *sprite = fill_sprite(hp);
+                    }
+                }
+            }
+            None if hp.current() < hp.max() => {
+                commands.entity(mob).with_children(|parent| {
+                    parent
+                        .spawn((
+                            SpriteBundle {
+                                sprite: Sprite {
+                                    color: Color::BLACK,
+                                    custom_size: Some(Vec2::new(BAR_WIDTH, BAR_HEIGHT)),
+                                    ..Default::default()
+                                },
+                                // Along the top of the monster's tile, just in front of it
+                                transform: Transform::from_xyz(
+                                    0.0,
+                                    (TILE_SIZE_F32 - BAR_HEIGHT) / 2.0,
+                                    0.5,
+                                ),
+                                ..Default::default()
+                            },
+                            HealthBar,
+                        ))
+                        .with_children(|bar| {
+                            bar.spawn((
+                                SpriteBundle {
+                                    sprite: fill_sprite(hp),
+                                    transform: Transform::from_xyz(-BAR_WIDTH / 2.0, 0.0, 0.1),
+                                    ..Default::default()
+                                },
+                                HealthBarFill,
+                            ));
+                        });
+                });
+            }
+            None => {}
+        }
+    }
+}
@@ -2,11 +2,14 @@ use bevy::prelude::*;
 use itertools::Itertools;
 
 use crate::{
-    inventory::{Inventory, InventoryIdx},
-    items::{ItemId, ItemList, UseItem},
+    input_manager::{Action, Actions, KeyMap},
+    inventory::{Autopickup, DropItem, Inventory, InventoryIdx, ItemStack},
+    items::{ItemList, UseItem},
     setup::Player,
 };
 
+use super::{first_key_label, throw_target_ui::ItemToThrow, GameUi, Messages};
+
 const INVENTORY_TILE_SIZE: f32 = 72.0;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
@@ -22,12 +25,27 @@ pub(super) fn spawn_inventory_ui(mut redraw_evt: EventWriter<RedrawInventoryUi>)
     redraw_evt.send_default();
 }
 
+/// What can be done with the items, with the keys currently bound to each
+fn controls_hint(keymap: &KeyMap) -> String {
+    let key = |action| first_key_label(keymap, action);
+
+    format!(
+        "Click: use  {}: drop  {}: throw  {}: autopickup",
+        key(Action::Drop),
+        key(Action::Throw),
+        key(Action::ToggleAutopickup)
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(super) fn build_inventory_ui(
     mut redraw_evt: EventReader<RedrawInventoryUi>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     player_inventory: Query<&Inventory, With<Player>>,
     item_list: Res<ItemList>,
+    autopickup: Res<Autopickup>,
+    keymap: Res<KeyMap>,
     inventory_ui_qry: Query<Entity, With<InventoryUi>>,
 ) {
     // Check for our redraw event and short-circuit if there isn't one
@@ -94,6 +112,14 @@ pub(super) fn build_inventory_ui(
                                 color: Color::WHITE,
                             },
                         ));
+                        header.spawn(TextBundle::from_section(
+                            controls_hint(&keymap),
+                            TextStyle {
+                                font: font_handle.clone(),
+                                font_size: 12.0,
+                                color: Color::GRAY,
+                            },
+                        ));
                     });
 
                     // Inventory items
@@ -106,7 +132,13 @@ pub(super) fn build_inventory_ui(
                         .map_into::<Option<_>>()
                         .pad_using(25, |_| None)
                     {
-                        spawn_item_cell(grid, item, &item_list, font_handle.clone(), &asset_server);
+                        spawn_item_cell(
+                            grid,
+                            item,
+                            (&item_list, &autopickup),
+                            font_handle.clone(),
+                            &asset_server,
+                        );
                     }
                 });
         });
@@ -114,8 +146,8 @@ pub(super) fn build_inventory_ui(
 
 fn spawn_item_cell(
     grid: &mut ChildBuilder,
-    item: Option<(InventoryIdx, &ItemId)>,
-    item_list: &ItemList,
+    item: Option<(InventoryIdx, &ItemStack)>,
+    (item_list, autopickup): (&ItemList, &Autopickup),
     font_handle: Handle<Font>,
     asset_server: &AssetServer,
 ) {
@@ -147,15 +179,53 @@ fn spawn_item_cell(
     }
 
     cell.with_children(|cell| {
-        if let Some((_, item)) = item {
+        if let Some((_, &ItemStack { item, count })) = item {
             // Place the image first so it lies underneath the text we'll spawn next
             cell.spawn(ImageBundle {
-                image: asset_server
-                    .load("sprites/items/potions/brilliant_blue.png")
-                    .into(),
+                image: asset_server.load(item_list[item].sprite()).into(),
                 ..Default::default()
             });
 
+            // How many are in the stack, if there's more than one
+            if count > 1 {
+                cell.spawn(
+                    TextBundle::from_section(
+                        format!("x{count}"),
+                        TextStyle {
+                            font: font_handle.clone(),
+                            font_size: 16.0,
+                            color: Color::YELLOW,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(2.0),
+                        right: Val::Px(4.0),
+                        ..Default::default()
+                    }),
+                );
+            }
+
+            // Whether walking over more of it picks it up
+            if !autopickup.is_enabled(item) {
+                cell.spawn(
+                    TextBundle::from_section(
+                        "no auto",
+                        TextStyle {
+                            font: font_handle.clone(),
+                            font_size: 10.0,
+                            color: Color::ORANGE,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(2.0),
+                        left: Val::Px(4.0),
+                        ..Default::default()
+                    }),
+                );
+            }
+
             cell.spawn(NodeBundle {
                 style: Style {
                     position_type: PositionType::Absolute,
@@ -189,18 +259,74 @@ pub(super) fn inventory_interaction(
     >,
     mut use_item_evt: EventWriter<UseItem>,
 ) {
+    let Ok((player, inventory)) = player_qry.get_single() else {
+        return;
+    };
+
     for (interaction, idx, mut border) in cell_qry.iter_mut() {
         match *interaction {
             Interaction::None => *border = Color::BLACK.into(),
             Interaction::Hovered => *border = Color::YELLOW.into(),
             Interaction::Pressed => {
                 *border = Color::GREEN.into();
-                let (player, inventory) = player_qry.get_single().unwrap();
+                // The cell may be out of date, as in `inventory_actions`
+                let Some(stack) = inventory.get(*idx) else {
+                    continue;
+                };
                 use_item_evt.send(UseItem {
-                    item: inventory[idx],
+                    item: stack.item,
                     user: player,
                 });
             }
         }
     }
 }
+
+/// Drop, throw, or toggle autopickup for the item under the mouse
+#[allow(clippy::too_many_arguments)]
+pub(super) fn inventory_actions(
+    actions: Res<Actions>,
+    player_qry: Query<(Entity, &Inventory), With<Player>>,
+    cell_qry: Query<(&Interaction, &InventoryIdx), With<InventoryCell>>,
+    item_list: Res<ItemList>,
+    mut autopickup: ResMut<Autopickup>,
+    mut item_to_throw: ResMut<ItemToThrow>,
+    mut drop_evt: EventWriter<DropItem>,
+    mut redraw_evt: EventWriter<RedrawInventoryUi>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+    mut messages: ResMut<Messages>,
+) {
+    let Some(&idx) = cell_qry
+        .iter()
+        .find(|&(interaction, _)| *interaction == Interaction::Hovered)
+        .map(|(_, idx)| idx)
+    else {
+        return;
+    };
+    let Ok((player, inventory)) = player_qry.get_single() else {
+        return;
+    };
+    // The cells can be a frame behind the inventory, e.g. just after the last of a stack is used up
+    let Some(stack) = inventory.get(idx) else {
+        return;
+    };
+    let item = stack.item;
+
+    if actions.perform(Action::Drop) {
+        drop_evt.send(DropItem {
+            item,
+            dropper: player,
+        });
+    } else if actions.perform(Action::Throw) {
+        item_to_throw.0 = Some(item);
+        ui_state.set(GameUi::TargetThrow);
+    } else if actions.perform(Action::ToggleAutopickup) {
+        let name = &item_list[item].name;
+        if autopickup.toggle(item) {
+            messages.add(format!("Autopickup enabled for {name}"));
+        } else {
+            messages.add(format!("Autopickup disabled for {name}"));
+        }
+        redraw_evt.send_default();
+    }
+}
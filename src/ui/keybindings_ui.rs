@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::input_manager::{Action, ActionModifier, Actions, BoundKey, KeyMap};
+
+use super::{first_key_label, GameUi, Messages};
+
+/// How many actions are listed at once, which is as many as fit on a small screen
+const VISIBLE_ACTIONS: usize = 16;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct KeybindingsUi;
+
+/// State of the key bindings screen
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub(super) struct KeybindingsMenu {
+    /// Index into [`Action::ALL`] of the selected action
+    selected: usize,
+    /// Index of the selected key among the action's bindings, or one past the last of them for
+    /// adding a new binding
+    slot: usize,
+    /// Whether we're waiting for the player to press the new key for the selected slot
+    capturing: bool,
+    /// A note about the most recent rebinding, e.g. a key taken from another action
+    notice: Option<String>,
+}
+
+pub(super) fn init_keybindings_menu(mut commands: Commands) {
+    commands.init_resource::<KeybindingsMenu>();
+}
+
+pub(super) fn remove_keybindings_menu(mut commands: Commands) {
+    commands.remove_resource::<KeybindingsMenu>();
+}
+
+/// Human-friendly name of an action, e.g. "Walk Northeast" for [`Action::WalkNortheast`]
+fn action_label(action: Action) -> String {
+    let name = format!("{action:?}");
+    let mut label = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            label.push(' ');
+        }
+        label.push(c);
+    }
+
+    label
+}
+
+/// The keys bound to an action, as shown to the player
+fn keys_label(keymap: &KeyMap, action: Action) -> String {
+    match keymap.bindings(action) {
+        [] => "(unbound)".to_string(),
+        keys => keys.iter().join(", "),
+    }
+}
+
+/// The key newly pressed this frame, along with any modifier being held, ignoring modifier keys
+fn captured_key(keys: &Input<KeyCode>) -> Option<BoundKey> {
+    let modifier_keys = ActionModifier::ALL.map(|modifier| modifier.key_codes());
+    let key = keys
+        .get_just_pressed()
+        .find(|key| !modifier_keys.iter().flatten().contains(*key))
+        .copied()?;
+
+    Some(
+        match ActionModifier::ALL
+            .into_iter()
+            .find(|modifier| keys.any_pressed(modifier.key_codes()))
+        {
+            Some(with) => BoundKey::ModifiedKey { key, with },
+            None => BoundKey::Key(key),
+        },
+    )
+}
+
+/// Save the keymap, letting the player know if that didn't work
+fn save_keymap(keymap: &KeyMap, messages: &mut Messages) {
+    match keymap.save() {
+        Ok(path) => info!("Saved keymap to {}", path.display()),
+        Err(err) => {
+            error!("Could not save keymap: {err}");
+            messages.add_hostile(format!("Could not save keymap: {err}"));
+        }
+    }
+}
+
+pub(super) fn keybindings_input(
+    actions: Res<Actions>,
+    keys: Res<Input<KeyCode>>,
+    mut menu: ResMut<KeybindingsMenu>,
+    mut keymap: ResMut<KeyMap>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+    mut messages: ResMut<Messages>,
+) {
+    let action = Action::ALL[menu.selected];
+    let mut bindings = keymap.bindings(action).to_vec();
+
+    if menu.capturing {
+        let Some(key) = captured_key(&keys) else {
+            return;
+        };
+        menu.capturing = false;
+
+        // Escape always backs out, so a binding can never be lost by accident
+        if key == BoundKey::Key(KeyCode::Escape) || bindings.contains(&key) {
+            return;
+        }
+
+        // A key can only do one thing, so it's taken away from whatever had it before
+        let taken_from = keymap.unbind(key).into_iter().map(action_label).join(", ");
+        menu.notice = (!taken_from.is_empty()).then(|| format!("{key} taken from {taken_from}"));
+
+        match bindings.get_mut(menu.slot) {
+            Some(bound) => *bound = key,
+            None => bindings.push(key),
+        }
+        keymap.set_bindings(action, bindings);
+        save_keymap(&keymap, &mut messages);
+    } else if actions.perform(Action::Cancel) || actions.perform(Action::OpenKeybindings) {
+        ui_state.set(GameUi::Main);
+    } else if actions.perform(Action::Confirm) {
+        menu.capturing = true;
+        menu.notice = None;
+    } else if actions.perform(Action::RemoveBinding) {
+        if menu.slot < bindings.len() {
+            bindings.remove(menu.slot);
+            keymap.set_bindings(action, bindings);
+            save_keymap(&keymap, &mut messages);
+            menu.notice = None;
+        }
+    } else if actions.perform(Action::WalkWest) {
+        menu.slot = menu.slot.saturating_sub(1);
+    } else if actions.perform(Action::WalkEast) {
+        menu.slot = (menu.slot + 1).min(bindings.len());
+    } else if actions.perform(Action::WalkNorth) {
+        menu.selected = menu
+            .selected
+            .checked_sub(1)
+            .unwrap_or(Action::ALL.len() - 1);
+        menu.slot = 0;
+    } else if actions.perform(Action::WalkSouth) {
+        menu.selected = (menu.selected + 1) % Action::ALL.len();
+        menu.slot = 0;
+    }
+
+    // Removing the last key leaves the selection past the end
+    let slots = keymap.bindings(Action::ALL[menu.selected]).len();
+    if menu.slot > slots {
+        menu.slot = slots;
+    }
+}
+
+/// The keys bound to the selected action, with the selected one picked out, followed by a slot
+/// for adding another
+fn selected_keys_label(keymap: &KeyMap, menu: &KeybindingsMenu) -> String {
+    let bindings = keymap.bindings(Action::ALL[menu.selected]);
+    let slot_label = |slot: usize, label: String| {
+        if slot != menu.slot {
+            label
+        } else if menu.capturing {
+            "[Press a key... (Escape to cancel)]".to_string()
+        } else {
+            format!("[{label}]")
+        }
+    };
+
+    bindings
+        .iter()
+        .map(ToString::to_string)
+        .chain(["+".to_string()])
+        .enumerate()
+        .map(|(slot, label)| slot_label(slot, label))
+        .join(", ")
+}
+
+pub(super) fn build_keybindings_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu: Res<KeybindingsMenu>,
+    keymap: Res<KeyMap>,
+    keybindings_ui_qry: Query<Entity, With<KeybindingsUi>>,
+) {
+    if !menu.is_changed() && !keymap.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in keybindings_ui_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_style = |color: Color| TextStyle {
+        font: font_handle.clone(),
+        font_size: 24.0,
+        color,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                // Shown over the top of e.g. the main menu
+                z_index: ZIndex::Global(1),
+                ..Default::default()
+            },
+            KeybindingsUi,
+        ))
+        .with_children(|container| {
+            container
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(15.0)),
+                        ..Default::default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                    ..Default::default()
+                })
+                .with_children(|list| {
+                    list.spawn(TextBundle::from_section(
+                        "Key Bindings",
+                        TextStyle {
+                            font: font_handle.clone(),
+                            font_size: 32.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+
+                    // Scroll the list along with the selection, keeping it near the middle
+                    let start = menu
+                        .selected
+                        .saturating_sub(VISIBLE_ACTIONS / 2)
+                        .min(Action::ALL.len().saturating_sub(VISIBLE_ACTIONS));
+                    let end = (start + VISIBLE_ACTIONS).min(Action::ALL.len());
+
+                    if start > 0 {
+                        list.spawn(TextBundle::from_section("...", text_style(Color::GRAY)));
+                    }
+
+                    for (i, action) in Action::ALL.into_iter().enumerate().take(end).skip(start) {
+                        let selected = i == menu.selected;
+                        let keys = if selected {
+                            selected_keys_label(&keymap, &menu)
+                        } else {
+                            keys_label(&keymap, action)
+                        };
+
+                        list.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(640.0),
+                                justify_content: JustifyContent::SpaceBetween,
+                                ..Default::default()
+                            },
+                            background_color: if selected {
+                                Color::DARK_GRAY.into()
+                            } else {
+                                Color::NONE.into()
+                            },
+                            ..Default::default()
+                        })
+                        .with_children(|row| {
+                            row.spawn(TextBundle::from_section(
+                                action_label(action),
+                                text_style(Color::WHITE),
+                            ));
+                            row.spawn(TextBundle::from_section(keys, text_style(Color::YELLOW)));
+                        });
+                    }
+
+                    if end < Action::ALL.len() {
+                        list.spawn(TextBundle::from_section("...", text_style(Color::GRAY)));
+                    }
+
+                    if let Some(notice) = &menu.notice {
+                        list.spawn(TextBundle::from_section(
+                            notice.clone(),
+                            text_style(Color::ORANGE),
+                        ));
+                    }
+
+                    list.spawn(TextBundle::from_section(
+                        format!(
+                            "{}/{} to pick a key, {} to rebind it, {} to remove it, {} to close",
+                            first_key_label(&keymap, Action::WalkWest),
+                            first_key_label(&keymap, Action::WalkEast),
+                            first_key_label(&keymap, Action::Confirm),
+                            first_key_label(&keymap, Action::RemoveBinding),
+                            first_key_label(&keymap, Action::Cancel),
+                        ),
+                        text_style(Color::GRAY),
+                    ));
+                });
+        });
+}
@@ -0,0 +1,148 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    input_manager::{Action, Actions},
+    morgue::HighScores,
+    record::RunRecord,
+    setup::Player,
+    GameState,
+};
+
+use super::{
+    menu::{menu_backdrop, navigate_menu, spawn_menu_entries, MenuEntry, MenuSelection},
+    GameUi,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct MainMenuUi;
+
+/// The choices on the main menu, in the order they're shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MainMenuEntry {
+    NewGame,
+    Continue,
+    Options,
+    Quit,
+}
+
+impl MainMenuEntry {
+    const ALL: [MainMenuEntry; 4] = [Self::NewGame, Self::Continue, Self::Options, Self::Quit];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::NewGame => "New Game",
+            Self::Continue => "Continue",
+            Self::Options => "Options",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+/// Whether there's a game in progress to go back to
+fn can_continue(record: &RunRecord, player_qry: &Query<(), With<Player>>) -> bool {
+    !player_qry.is_empty() && !record.is_over()
+}
+
+fn entries_enabled(record: &RunRecord, player_qry: &Query<(), With<Player>>) -> [bool; 4] {
+    MainMenuEntry::ALL
+        .map(|entry| entry != MainMenuEntry::Continue || can_continue(record, player_qry))
+}
+
+pub(super) fn build_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selection: Res<MenuSelection>,
+    record: Res<RunRecord>,
+    high_scores: Res<HighScores>,
+    player_qry: Query<(), With<Player>>,
+    main_menu_qry: Query<Entity, With<MainMenuUi>>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in main_menu_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let enabled = entries_enabled(&record, &player_qry);
+    let entries: Vec<_> = MainMenuEntry::ALL
+        .iter()
+        .zip(enabled)
+        .map(|(entry, enabled)| (entry.label(), enabled))
+        .collect();
+
+    commands
+        .spawn((menu_backdrop(), MainMenuUi))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                "Swords and Runes",
+                TextStyle {
+                    font: font_handle.clone(),
+                    font_size: 64.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            spawn_menu_entries(menu, &entries, *selection, font_handle.clone());
+
+            if !high_scores.is_empty() {
+                let text_style = |font_size: f32| TextStyle {
+                    font: font_handle.clone(),
+                    font_size,
+                    color: Color::GRAY,
+                };
+
+                menu.spawn(
+                    TextBundle::from_section("High Scores", text_style(32.0)).with_style(Style {
+                        margin: UiRect::top(Val::Px(30.0)),
+                        ..Default::default()
+                    }),
+                );
+                for (rank, high_score) in high_scores.iter().enumerate() {
+                    menu.spawn(TextBundle::from_section(
+                        format!(
+                            "{:>2}. {:>5}  {}, depth {}, {} turns",
+                            rank + 1,
+                            high_score.score,
+                            high_score.cause_of_death,
+                            high_score.depth,
+                            high_score.turns,
+                        ),
+                        text_style(20.0),
+                    ));
+                }
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn main_menu_input(
+    actions: Res<Actions>,
+    entry_qry: Query<(&Interaction, &MenuEntry), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+    record: Res<RunRecord>,
+    player_qry: Query<(), With<Player>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    // Escape heads back into the game, if there is one
+    if actions.perform(Action::Cancel) && can_continue(&record, &player_qry) {
+        game_state.set(GameState::Running);
+        return;
+    }
+
+    let enabled = entries_enabled(&record, &player_qry);
+    match navigate_menu(&actions, &entry_qry, &mut selection, &enabled)
+        .map(|idx| MainMenuEntry::ALL[idx])
+    {
+        Some(MainMenuEntry::NewGame) => game_state.set(GameState::AssetsLoading),
+        Some(MainMenuEntry::Continue) => game_state.set(GameState::Running),
+        Some(MainMenuEntry::Options) => ui_state.set(GameUi::Keybindings),
+        Some(MainMenuEntry::Quit) => exit.send(AppExit),
+        None => {}
+    }
+}
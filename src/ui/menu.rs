@@ -0,0 +1,142 @@
+//! Pieces shared by the full-screen menus, e.g. the main menu
+//!
+//! A menu is a column of entries, picked with the walk keys and [`Action::Confirm`] or by clicking.
+
+use bevy::prelude::*;
+
+use crate::input_manager::{Action, Actions};
+
+/// Color of the menu entry currently selected
+const SELECTED_COLOR: Color = Color::YELLOW;
+/// Color of menu entries that can't be picked right now
+const DISABLED_COLOR: Color = Color::DARK_GRAY;
+
+/// A clickable menu entry, by its index in the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct MenuEntry(usize);
+
+/// The menu entry currently selected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub(super) struct MenuSelection(pub usize);
+
+pub(super) fn init_menu_selection(mut commands: Commands) {
+    commands.init_resource::<MenuSelection>();
+}
+
+pub(super) fn remove_menu_selection(mut commands: Commands) {
+    commands.remove_resource::<MenuSelection>();
+}
+
+/// A full-screen backdrop for a menu, covering the dungeon behind it
+pub(super) fn menu_backdrop() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(10.0),
+            ..Default::default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.95).into(),
+        ..Default::default()
+    }
+}
+
+/// Spawn a menu's entries, given their labels and whether each can be picked
+pub(super) fn spawn_menu_entries(
+    parent: &mut ChildBuilder,
+    entries: &[(&str, bool)],
+    selection: MenuSelection,
+    font: Handle<Font>,
+) {
+    for (idx, &(label, enabled)) in entries.iter().enumerate() {
+        let color = if !enabled {
+            DISABLED_COLOR
+        } else if idx == selection.0 {
+            SELECTED_COLOR
+        } else {
+            Color::WHITE
+        };
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(10.0), Val::Px(5.0)),
+                        ..Default::default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..Default::default()
+                },
+                MenuEntry(idx),
+            ))
+            .with_children(|button| {
+                button.spawn(TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 32.0,
+                        color,
+                    },
+                ));
+            });
+    }
+}
+
+/// Move the selection through a menu, returning the entry picked, if any
+///
+/// `enabled` says whether each entry can be picked; the selection skips over those that can't.
+pub(super) fn navigate_menu(
+    actions: &Actions,
+    entry_qry: &Query<(&Interaction, &MenuEntry), Changed<Interaction>>,
+    selection: &mut ResMut<MenuSelection>,
+    enabled: &[bool],
+) -> Option<usize> {
+    let step = |from: usize, forward: bool| {
+        (1..=enabled.len())
+            .map(|offset| {
+                if forward {
+                    (from + offset) % enabled.len()
+                } else {
+                    (from + enabled.len() - offset) % enabled.len()
+                }
+            })
+            .find(|&idx| enabled[idx])
+            .unwrap_or(from)
+    };
+
+    let mut selected = selection.0;
+    if !enabled.get(selected).copied().unwrap_or(false) {
+        selected = step(selected, true);
+    }
+
+    let mut picked = None;
+    for (interaction, &MenuEntry(idx)) in entry_qry.iter() {
+        if !enabled[idx] {
+            continue;
+        }
+        match *interaction {
+            Interaction::Pressed => picked = Some(idx),
+            Interaction::Hovered => selected = idx,
+            Interaction::None => {}
+        }
+    }
+
+    if picked.is_none() {
+        if actions.perform(Action::WalkNorth) {
+            selected = step(selected, false);
+        } else if actions.perform(Action::WalkSouth) {
+            selected = step(selected, true);
+        } else if actions.perform(Action::Confirm) {
+            picked = Some(selected);
+        }
+    }
+
+    // Only touch the selection when it moves, since menus are rebuilt when it changes
+    selection.set_if_neq(MenuSelection(selected));
+
+    picked
+}
@@ -0,0 +1,218 @@
+use bevy::{input::mouse::MouseWheel, prelude::*};
+use itertools::Itertools;
+
+use crate::input_manager::{Action, Actions, KeyMap};
+
+use super::{
+    first_key_label,
+    messages::{Message, MessageLevel},
+    Messages,
+};
+
+/// How many lines of history fit on the screen at once
+const HISTORY_LINES: usize = 24;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct MessageHistoryUi;
+
+/// State of the message history screen
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub(super) struct MessageHistory {
+    /// How many lines the view is scrolled up from the newest message
+    scroll: usize,
+    /// Only show messages of this level, or everything if there isn't one
+    filter: Option<MessageLevel>,
+}
+
+impl MessageHistory {
+    /// Move on to filtering by the next level, wrapping back round to showing everything
+    fn cycle_filter(&mut self) {
+        self.filter = match self.filter {
+            None => Some(MessageLevel::ALL[0]),
+            Some(level) => MessageLevel::ALL
+                .into_iter()
+                .skip_while(|&other| other != level)
+                .nth(1),
+        };
+        self.scroll = 0;
+    }
+
+    fn filter_label(&self) -> &'static str {
+        self.filter.map_or("All", |level| level.name())
+    }
+}
+
+/// A line in the history, either the start of a turn or a message from it
+enum HistoryLine<'a> {
+    Turn(u32),
+    Message(&'a Message),
+}
+
+/// The messages passing the filter, oldest first, with a heading each time a new turn starts
+fn history_lines(messages: &Messages, filter: Option<MessageLevel>) -> Vec<HistoryLine<'_>> {
+    messages
+        .iter()
+        .filter(|message| filter.is_none_or(|level| message.level() == level))
+        .group_by(|message| message.turn())
+        .into_iter()
+        .flat_map(|(turn, group)| {
+            std::iter::once(HistoryLine::Turn(turn)).chain(group.map(HistoryLine::Message))
+        })
+        .collect()
+}
+
+pub(super) fn init_message_history(mut commands: Commands) {
+    commands.init_resource::<MessageHistory>();
+}
+
+pub(super) fn remove_message_history(mut commands: Commands) {
+    commands.remove_resource::<MessageHistory>();
+}
+
+pub(super) fn message_history_input(
+    actions: Res<Actions>,
+    mut wheel_evt: EventReader<MouseWheel>,
+    messages: Res<Messages>,
+    mut history: ResMut<MessageHistory>,
+) {
+    if actions.perform(Action::CycleTarget) {
+        history.cycle_filter();
+        return;
+    }
+
+    let mut scroll = history.scroll as isize;
+    if actions.perform(Action::WalkNorth) {
+        scroll += 1;
+    } else if actions.perform(Action::WalkSouth) {
+        scroll -= 1;
+    } else if actions.perform(Action::RunNorth) {
+        scroll += HISTORY_LINES as isize;
+    } else if actions.perform(Action::RunSouth) {
+        scroll -= HISTORY_LINES as isize;
+    }
+    for wheel in wheel_evt.iter() {
+        scroll += wheel.y.signum() as isize;
+    }
+
+    let line_count = history_lines(&messages, history.filter).len();
+    let max_scroll = line_count.saturating_sub(HISTORY_LINES);
+    let scroll = (scroll.max(0) as usize).min(max_scroll);
+    history
+        .map_unchanged(|history| &mut history.scroll)
+        .set_if_neq(scroll);
+}
+
+pub(super) fn build_message_history_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    history: Res<MessageHistory>,
+    messages: Res<Messages>,
+    keymap: Res<KeyMap>,
+    message_history_ui_qry: Query<Entity, With<MessageHistoryUi>>,
+) {
+    if !history.is_changed() && !messages.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in message_history_ui_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_style = |color: Color| TextStyle {
+        font: font_handle.clone(),
+        font_size: 20.0,
+        color,
+    };
+
+    let lines = history_lines(&messages, history.filter);
+    let end = lines.len().saturating_sub(history.scroll);
+    let start = end.saturating_sub(HISTORY_LINES);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MessageHistoryUi,
+        ))
+        .with_children(|container| {
+            container
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        width: Val::Px(800.0),
+                        padding: UiRect::all(Val::Px(15.0)),
+                        ..Default::default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                    ..Default::default()
+                })
+                .with_children(|list| {
+                    list.spawn(TextBundle::from_section(
+                        format!("Message History ({})", history.filter_label()),
+                        TextStyle {
+                            font: font_handle.clone(),
+                            font_size: 32.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+
+                    if start > 0 {
+                        list.spawn(TextBundle::from_section("...", text_style(Color::GRAY)));
+                    }
+
+                    for line in &lines[start..end] {
+                        list.spawn(match line {
+                            HistoryLine::Turn(turn) => TextBundle::from_section(
+                                format!("Turn {turn}"),
+                                text_style(Color::GRAY),
+                            )
+                            .with_style(Style {
+                                margin: UiRect::top(Val::Px(6.0)),
+                                ..Default::default()
+                            }),
+                            HistoryLine::Message(message) => TextBundle::from_section(
+                                format!("  {}", message.text()),
+                                text_style(message.level().color()),
+                            ),
+                        });
+                    }
+
+                    if lines.is_empty() {
+                        list.spawn(TextBundle::from_section(
+                            "No messages",
+                            text_style(Color::GRAY),
+                        ));
+                    } else if end < lines.len() {
+                        list.spawn(TextBundle::from_section("...", text_style(Color::GRAY)));
+                    }
+
+                    list.spawn(
+                        TextBundle::from_section(
+                            format!(
+                                "{}/{} to scroll, {} to filter, {} to close",
+                                first_key_label(&keymap, Action::WalkNorth),
+                                first_key_label(&keymap, Action::WalkSouth),
+                                first_key_label(&keymap, Action::CycleTarget),
+                                first_key_label(&keymap, Action::Cancel),
+                            ),
+                            text_style(Color::GRAY),
+                        )
+                        .with_style(Style {
+                            margin: UiRect::top(Val::Px(10.0)),
+                            ..Default::default()
+                        }),
+                    );
+                });
+        });
+}
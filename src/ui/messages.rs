@@ -1,9 +1,16 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
 use itertools::Itertools;
 
+/// How many messages are kept; the oldest are dropped to make room for new ones
+const MAX_MESSAGES: usize = 1000;
+
 #[derive(Debug, Default, Clone, Eq, PartialEq, Resource)]
 pub struct Messages {
-    messages: Vec<Message>,
+    messages: VecDeque<Message>,
+    /// The turn new messages are recorded against
+    turn: u32,
 }
 
 impl Messages {
@@ -11,20 +18,47 @@ impl Messages {
         Self::default()
     }
 
+    fn push(&mut self, mut message: Message) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+
+        message.turn = self.turn;
+        self.messages.push_back(message);
+    }
+
     pub fn add<S: Into<String>>(&mut self, message: S) {
-        self.messages.push(Message::new(message));
+        self.push(Message::new(message));
     }
 
     pub fn add_hostile<S: Into<String>>(&mut self, message: S) {
-        self.messages.push(Message::hostile(message));
+        self.push(Message::hostile(message));
     }
 
     pub fn add_friendly<S: Into<String>>(&mut self, message: S) {
-        self.messages.push(Message::friendly(message));
+        self.push(Message::friendly(message));
     }
 
     pub fn add_notice<S: Into<String>>(&mut self, message: S) {
-        self.messages.push(Message::notice(message));
+        self.push(Message::notice(message));
+    }
+
+    /// Record any new messages against the given turn
+    pub fn set_turn(&mut self, turn: u32) {
+        self.turn = turn;
+    }
+
+    /// Every message still kept, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Message> {
+        self.messages.iter()
+    }
+
+    /// The most recent messages, oldest first
+    pub fn last(&self, n: usize) -> impl Iterator<Item = &str> {
+        self.messages
+            .iter()
+            .skip(self.messages.len().saturating_sub(n))
+            .map(|message| message.message.as_str())
     }
 
     pub fn text_sections_rev(&self, font: Handle<Font>) -> impl Iterator<Item = TextSection> + '_ {
@@ -51,16 +85,31 @@ impl Messages {
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
-struct Message {
+pub struct Message {
     message: String,
     level: MessageLevel,
+    /// The turn the message was added on
+    turn: u32,
 }
 
 impl Message {
+    pub fn text(&self) -> &str {
+        &self.message
+    }
+
+    pub fn level(&self) -> MessageLevel {
+        self.level
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
     fn new<S: Into<String>>(message: S) -> Self {
         Self {
             message: message.into(),
             level: MessageLevel::Default,
+            turn: 0,
         }
     }
 
@@ -68,6 +117,7 @@ impl Message {
         Self {
             message: message.into(),
             level: MessageLevel::Hostile,
+            turn: 0,
         }
     }
 
@@ -75,6 +125,7 @@ impl Message {
         Self {
             message: message.into(),
             level: MessageLevel::Friendly,
+            turn: 0,
         }
     }
 
@@ -82,12 +133,13 @@ impl Message {
         Self {
             message: message.into(),
             level: MessageLevel::Notice,
+            turn: 0,
         }
     }
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
-enum MessageLevel {
+pub enum MessageLevel {
     #[default]
     Default,
     Hostile,
@@ -96,7 +148,23 @@ enum MessageLevel {
 }
 
 impl MessageLevel {
-    fn color(&self) -> Color {
+    pub const ALL: [MessageLevel; 4] = [
+        MessageLevel::Default,
+        MessageLevel::Hostile,
+        MessageLevel::Friendly,
+        MessageLevel::Notice,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MessageLevel::Default => "General",
+            MessageLevel::Hostile => "Hostile",
+            MessageLevel::Friendly => "Friendly",
+            MessageLevel::Notice => "Notice",
+        }
+    }
+
+    pub fn color(&self) -> Color {
         match *self {
             MessageLevel::Default => Color::WHITE,
             MessageLevel::Hostile => Color::RED,
@@ -0,0 +1,119 @@
+//! Choosing what to pick up when there's more than one kind of item underfoot
+
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::{
+    dungeon::TilePos,
+    input_manager::Actions,
+    inventory::PickUpItems,
+    items::{ItemId, ItemList},
+    setup::Player,
+};
+
+use super::{
+    menu::{menu_backdrop, navigate_menu, spawn_menu_entries, MenuEntry, MenuSelection},
+    GameUi,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct PickUpUi;
+
+/// The items on the player's tile, grouped by kind in the order they're listed
+fn items_here(
+    player_qry: &Query<(Entity, &TilePos), With<Player>>,
+    items_qry: &Query<(Entity, &TilePos, &ItemId)>,
+) -> Vec<(ItemId, Vec<Entity>)> {
+    let Ok((_, &pos)) = player_qry.get_single() else {
+        return Vec::new();
+    };
+
+    items_qry
+        .iter()
+        .filter(|&(_, &item_pos, _)| item_pos == pos)
+        .map(|(item, _, &item_id)| (item_id, item))
+        .into_group_map()
+        .into_iter()
+        .sorted_unstable_by_key(|&(item_id, _)| item_id)
+        .collect()
+}
+
+pub(super) fn build_pick_up_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selection: Res<MenuSelection>,
+    item_list: Res<ItemList>,
+    player_qry: Query<(Entity, &TilePos), With<Player>>,
+    items_qry: Query<(Entity, &TilePos, &ItemId)>,
+    pick_up_qry: Query<Entity, With<PickUpUi>>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+
+    // Make sure we start with a clean slate
+    for ui in pick_up_qry.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    let font_handle: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let labels = items_here(&player_qry, &items_qry)
+        .into_iter()
+        .map(|(item_id, items)| match items.len() {
+            1 => item_list[item_id].name.clone(),
+            count => format!("{count} x {}", item_list[item_id].name),
+        })
+        .chain(["Everything".to_string()])
+        .collect_vec();
+    let entries = labels
+        .iter()
+        .map(|label| (label.as_str(), true))
+        .collect_vec();
+
+    commands
+        .spawn((menu_backdrop(), PickUpUi))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                "Pick Up",
+                TextStyle {
+                    font: font_handle.clone(),
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            spawn_menu_entries(menu, &entries, *selection, font_handle.clone());
+        });
+}
+
+pub(super) fn pick_up_input(
+    actions: Res<Actions>,
+    entry_qry: Query<(&Interaction, &MenuEntry), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+    player_qry: Query<(Entity, &TilePos), With<Player>>,
+    items_qry: Query<(Entity, &TilePos, &ItemId)>,
+    mut pick_up_evt: EventWriter<PickUpItems>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+) {
+    let Ok((player, _)) = player_qry.get_single() else {
+        return;
+    };
+    let mut groups = items_here(&player_qry, &items_qry);
+
+    // Every kind of item, followed by all of them at once
+    let enabled = vec![true; groups.len() + 1];
+    let Some(picked) = navigate_menu(&actions, &entry_qry, &mut selection, &enabled) else {
+        return;
+    };
+
+    let items = if picked < groups.len() {
+        groups.swap_remove(picked).1
+    } else {
+        groups.into_iter().flat_map(|(_, items)| items).collect()
+    };
+    pick_up_evt.send(PickUpItems {
+        picker: player,
+        items,
+    });
+    ui_state.set(GameUi::Main);
+}
@@ -1,259 +1,196 @@
 use std::collections::HashSet;
 
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::prelude::*;
 use itertools::Itertools;
 
 use crate::{
-    camera::PrimaryCamera,
+    animation::{Animation, AnimationQueue},
     combat::HP,
     dungeon::{BlocksMovement, BlocksSight, Map, TilePos, TILE_SIZE_F32},
     fieldofview::{compute_limited_fov, FieldOfView},
-    magic::{CastSpell, CastSpellOn, SpellTarget, SpellToCast},
+    magic::{SpellTarget, SpellToCast},
     utils::SpriteLayer,
 };
 
-use super::GameUi;
+use super::{
+    targeting::{TargetConfirmed, Targeting},
+    GameUi, Messages,
+};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
 pub(super) struct SpellTargetUi;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
-pub(super) struct SingleTarget(Entity);
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
-pub(super) struct TargetArea(i32);
-
-pub(super) fn init_spell_targeting(world: &mut World) {
-    if let Some(casting) = world.resource::<SpellToCast>().0 {
-        match casting.spell.target {
-            SpellTarget::Caster => world.resource_mut::<NextState<GameUi>>().set(GameUi::Main),
-            SpellTarget::Single => init_single_target_select(casting, world),
-            SpellTarget::Area(radius) => init_area_target_select(casting, radius, world),
-        }
-    } else {
-        world.resource_mut::<NextState<GameUi>>().set(GameUi::Main);
-    }
-}
-
-fn init_area_target_select(casting: CastSpell, radius: u8, world: &mut World) {
-    let from = world.get::<Transform>(casting.caster).unwrap();
-    let from_tile = TilePos::from(from);
-
-    world.spawn((
-        SpatialBundle::from_transform(from_tile.as_transform(SpriteLayer::UI)),
-        SpellTargetUi,
-        TargetArea(radius as i32),
-    ));
-}
-
-fn init_single_target_select(casting: CastSpell, world: &mut World) {
-    let (camera, &camera_transform) = world
-        .query_filtered::<(&Camera, &GlobalTransform), With<PrimaryCamera>>()
-        .get_single(world)
-        .unwrap();
-    // Hacky way to get around borrow checker if we keep the reference instead
-    // Consider rewriting this to use SystemState
-    let camera = camera.clone();
-
-    let from = world.get::<Transform>(casting.caster).unwrap();
-    let from_tile = TilePos::from(from);
-    let range = u32::from(casting.spell.range);
-
-    let mut targets = Vec::new();
-
-    for (target, target_pos) in world
-        .query_filtered::<(Entity, &Transform), With<HP>>()
-        .iter(world)
-    {
-        if target == casting.caster {
-            // Don't target yourself
-            continue;
-        }
-
-        let tile = TilePos::from(target_pos);
-        if from_tile.distance(tile) > range {
-            // Out of range, don't target this one
-            continue;
-        }
-
-        if world
-            .resource::<Map>()
-            .get(tile)
-            .and_then(|e| world.get::<FieldOfView>(e))
-            .is_some_and(|fov| *fov == FieldOfView::Visible)
-        {
-            let world_pos = tile.corner();
-            if let Some(screen_pos) =
-                camera.world_to_viewport(&camera_transform, world_pos.extend(0.0))
-            {
-                targets.push((target, screen_pos));
-            }
-        }
-    }
-
-    for (target, screen_pos) in targets {
-        world.spawn((
-            NodeBundle {
-                style: Style {
-                    width: Val::Px(TILE_SIZE_F32),
-                    height: Val::Px(TILE_SIZE_F32),
-                    position_type: PositionType::Absolute,
-                    top: Val::Px(screen_pos.y),
-                    left: Val::Px(screen_pos.x),
-                    border: UiRect::all(Val::Px(1.0)),
-                    ..Default::default()
-                },
-                border_color: Color::ALICE_BLUE.into(),
-                ..Default::default()
-            },
-            Interaction::default(),
-            SpellTargetUi,
-            SingleTarget(target),
-        ));
-    }
-}
+pub(super) struct TargetArea(pub(super) i32);
 
-#[allow(clippy::type_complexity)]
-pub(super) fn update_single_target_select(
-    mut targets_qry: Query<
-        (&Interaction, &mut BorderColor, Option<&SingleTarget>),
-        (With<SpellTargetUi>, Changed<Interaction>),
-    >,
+pub(super) fn init_spell_targeting(
+    mut commands: Commands,
     spell: Res<SpellToCast>,
-    mut spell_evt: EventWriter<CastSpellOn>,
+    targeting: Option<ResMut<Targeting>>,
     mut ui_state: ResMut<NextState<GameUi>>,
 ) {
-    for (interaction, mut border, single_target) in targets_qry.iter_mut() {
-        match *interaction {
-            Interaction::Pressed => {
-                // Cast the spell on the target
-                spell_evt.send(spell.on(single_target.unwrap().0));
-                // Close the spell target UI
-                ui_state.set(GameUi::Main);
+    if let (Some(casting), Some(mut targeting)) = (spell.0, targeting) {
+        targeting.range = Some(u32::from(casting.spell.range));
+
+        match casting.spell.target {
+            SpellTarget::Caster => ui_state.set(GameUi::Main),
+            SpellTarget::Single => {}
+            SpellTarget::Area(radius) => {
+                commands.spawn((
+                    SpatialBundle::default(),
+                    SpellTargetUi,
+                    TargetArea(radius as i32),
+                ));
             }
-            Interaction::Hovered => *border = Color::GREEN.into(),
-            Interaction::None => *border = Color::ALICE_BLUE.into(),
         }
+    } else {
+        ui_state.set(GameUi::Main);
     }
 }
 
 pub(super) fn update_area_target_select(
-    camera_qry: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
     mut commands: Commands,
-    mut cursor_evt: EventReader<CursorMoved>,
-    blocks_sight_qry: Query<&Transform, With<BlocksSight>>,
+    targeting: Res<Targeting>,
+    blocks_sight_qry: Query<&TilePos, With<BlocksSight>>,
     mut target_src: Query<(Entity, &TargetArea, &mut Transform), Without<BlocksSight>>,
     map: Res<Map>,
     targetable_tile: Query<&FieldOfView, Without<BlocksMovement>>,
 ) {
-    if let Ok((target, target_area, mut target_transform)) = target_src.get_single_mut() {
-        if let Some(cursor) = cursor_evt.iter().last() {
-            let (camera, camera_transform) = camera_qry.get_single().unwrap();
-            if let Some(cursor_pos) = camera.viewport_to_world_2d(camera_transform, cursor.position)
-            {
-                let target_tile = TilePos::from(cursor_pos);
-
-                if target_tile == TilePos::from(*target_transform) {
-                    // Cursor hasn't left the currently targeted tile
-                    return;
-                }
-
-                *target_transform = target_tile.as_transform(SpriteLayer::UI);
-                commands.entity(target).despawn_descendants();
+    if !targeting.is_changed() {
+        return;
+    }
 
-                if map
-                    .get(target_tile)
-                    .and_then(|tile_entity| targetable_tile.get(tile_entity).ok())
-                    .map(|fov| *fov != FieldOfView::Visible)
-                    .unwrap_or(true)
-                {
-                    // No FoV if the target itself isn't visible
-                    return;
-                }
+    if let Ok((target, target_area, mut target_transform)) = target_src.get_single_mut() {
+        let target_tile = targeting.cursor;
 
-                let blockers: HashSet<_> = blocks_sight_qry
-                    .iter()
-                    .map(|transform| TilePos::from(*transform))
-                    .collect();
+        *target_transform = target_tile.as_transform(SpriteLayer::UI);
+        commands.entity(target).despawn_descendants();
 
-                let area = compute_limited_fov(target_tile, target_area.0, |tile| {
-                    blockers.contains(&tile)
-                });
-                let target_origin = target_tile.as_vec();
+        if map
+            .get(target_tile)
+            .and_then(|tile_entity| targetable_tile.get(tile_entity).ok())
+            .map(|fov| *fov != FieldOfView::Visible)
+            .unwrap_or(true)
+        {
+            // No FoV if the target itself isn't visible
+            return;
+        }
 
-                commands.entity(target).with_children(|parent| {
-                    area.into_iter()
-                        .sorted_by_cached_key(|tile| (tile.x, tile.y))
-                        .dedup()
-                        .filter(|tile| {
-                            targetable_tile
-                                .get(map.get(*tile).unwrap())
-                                .is_ok_and(|fov| *fov == FieldOfView::Visible)
-                        })
-                        .for_each(|tile| {
-                            let relative_translation = tile.as_vec() - target_origin;
-                            parent.spawn(SpriteBundle {
-                                transform: Transform::from_translation(
-                                    relative_translation.extend(-0.1),
-                                ),
-                                sprite: Sprite {
-                                    color: Color::GREEN.with_a(0.15),
-                                    custom_size: Some(Vec2::splat(TILE_SIZE_F32)),
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            });
-                        });
+        let blockers: HashSet<_> = blocks_sight_qry.iter().copied().collect();
+
+        let area = compute_limited_fov(target_tile, target_area.0, |tile| blockers.contains(&tile));
+        let target_origin = target_tile.as_vec();
+        let color = if targeting.in_range() {
+            Color::GREEN.with_a(0.15)
+        } else {
+            Color::RED.with_a(0.15)
+        };
+
+        commands.entity(target).with_children(|parent| {
+            area.into_iter()
+                .sorted_by_cached_key(|tile| (tile.x, tile.y))
+                .dedup()
+                .filter(|tile| {
+                    map.get(*tile)
+                        .and_then(|tile_entity| targetable_tile.get(tile_entity).ok())
+                        .is_some_and(|fov| *fov == FieldOfView::Visible)
+                })
+                .for_each(|tile| {
+                    let relative_translation = tile.as_vec() - target_origin;
+                    parent.spawn(SpriteBundle {
+                        transform: Transform::from_translation(relative_translation.extend(-0.1)),
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(Vec2::splat(TILE_SIZE_F32)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    });
                 });
-            }
-        }
+        });
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub(super) fn fire_area_target_spell(
-    buttons: Res<Input<MouseButton>>,
-    window_qry: Query<&Window, With<PrimaryWindow>>,
-    camera_qry: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
-    blocks_sight_qry: Query<&Transform, With<BlocksSight>>,
+pub(super) fn fire_spell(
+    mut confirm_evt: EventReader<TargetConfirmed>,
+    targeting: Res<Targeting>,
     spell: Res<SpellToCast>,
-    targetable_qry: Query<(Entity, &Transform), With<HP>>,
-    mut spell_evt: EventWriter<CastSpellOn>,
+    map: Res<Map>,
+    fov_qry: Query<&FieldOfView>,
+    blocks_sight_qry: Query<&TilePos, With<BlocksSight>>,
+    targetable_qry: Query<(Entity, &TilePos), With<HP>>,
+    mut animations: ResMut<AnimationQueue>,
     mut ui_state: ResMut<NextState<GameUi>>,
+    mut messages: ResMut<Messages>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        if let Some(spell) = spell.0 {
-            let spell_area = match spell.spell.target {
-                SpellTarget::Caster | SpellTarget::Single => return,
-                SpellTarget::Area(area) => area,
+    let Some(TargetConfirmed(spell_tile)) = confirm_evt.iter().last().copied() else {
+        return;
+    };
+    let Some(casting) = spell.0 else {
+        return;
+    };
+
+    if !map
+        .get(spell_tile)
+        .and_then(|tile_entity| fov_qry.get(tile_entity).ok())
+        .is_some_and(|fov| *fov == FieldOfView::Visible)
+    {
+        messages.add("You can't see that spot.");
+        return;
+    }
+
+    if !targeting.in_range() {
+        messages.add("That target is out of range.");
+        return;
+    }
+
+    let color = casting.spell.effect.color();
+    match casting.spell.target {
+        SpellTarget::Caster => {}
+        SpellTarget::Single => {
+            let Some((target, _)) = targetable_qry
+                .iter()
+                .find(|&(target, &pos)| target != casting.caster && pos == spell_tile)
+            else {
+                messages.add("There's nothing there to target.");
+                return;
             };
-            if let Some(cursor_position) = window_qry.single().cursor_position() {
-                let (camera, camera_transform) = camera_qry.get_single().unwrap();
-                if let Some(cursor_pos) =
-                    camera.viewport_to_world_2d(camera_transform, cursor_position)
-                {
-                    let spell_tile = TilePos::from(cursor_pos);
-                    let blockers: HashSet<_> = blocks_sight_qry
-                        .iter()
-                        .map(|transform| TilePos::from(*transform))
-                        .collect();
-                    let area: HashSet<_> =
-                        compute_limited_fov(spell_tile, spell_area as i32, |tile| {
-                            blockers.contains(&tile)
-                        })
-                        .into_iter()
-                        .collect();
 
-                    for (target, target_transform) in targetable_qry.iter() {
-                        let target_tile = TilePos::from(target_transform);
-                        if area.contains(&target_tile) {
-                            spell_evt.send(spell.on(target));
-                        }
-                    }
+            animations.push(
+                Animation::bolt(targeting.origin, spell_tile, color).then([casting.on(target)]),
+            );
+        }
+        SpellTarget::Area(radius) => {
+            let blockers: HashSet<_> = blocks_sight_qry.iter().copied().collect();
+            let area: HashSet<_> =
+                compute_limited_fov(spell_tile, radius as i32, |tile| blockers.contains(&tile))
+                    .into_iter()
+                    .collect();
 
-                    ui_state.set(GameUi::Main);
-                }
+            let targets = targetable_qry
+                .iter()
+                .filter(|&(_, target_pos)| area.contains(target_pos))
+                .map(|(target, _)| target)
+                .collect_vec();
+            if targets.is_empty() {
+                // Don't waste the spell on an empty patch of floor
+                messages.add("There's nothing there to target.");
+                return;
             }
+
+            animations.push(Animation::bolt(targeting.origin, spell_tile, color));
+            animations.push(
+                Animation::burst(spell_tile, area.into_iter().collect(), color.with_a(0.5))
+                    .then(targets.into_iter().map(|target| casting.on(target))),
+            );
         }
     }
+
+    ui_state.set(GameUi::Main);
+}
+
+/// Forget about the spell once targeting ends, whether it was cast or abandoned
+pub(super) fn clear_spell_to_cast(mut spell_to_cast: ResMut<SpellToCast>) {
+    spell_to_cast.clear();
 }
@@ -0,0 +1,251 @@
+//! A keyboard-driven targeting cursor
+//!
+//! The cursor is shared by every UI that needs the player to pick a tile, e.g. spell targeting
+//! or simply looking around. It is moved with the walk keys (or the mouse), can cycle through
+//! visible hostiles, and reports the chosen tile with a [`TargetConfirmed`] event.
+
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::{
+    camera::PrimaryCamera,
+    dungeon::{Map, TilePos, TILE_SIZE_F32},
+    fieldofview::FieldOfView,
+    input_manager::{Action, Actions},
+    mobs::Mob,
+    setup::Player,
+    utils::SpriteLayer,
+};
+
+use super::{
+    dungeon_ui::{set_looking_at, LookingAt, TileDescriber},
+    GameUi,
+};
+
+/// Color of the targeting cursor
+const CURSOR_COLOR: Color = Color::rgba(1.0, 1.0, 0.0, 0.35);
+/// Color of the targeting cursor when it's beyond the allowed range
+const OUT_OF_RANGE_COLOR: Color = Color::rgba(1.0, 0.0, 0.0, 0.35);
+
+/// Marker component for the targeting cursor's sprite
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct TargetCursor;
+
+/// The current state of the targeting cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct Targeting {
+    /// Where the targeting started from, usually the player
+    pub origin: TilePos,
+    /// The tile the cursor is currently on
+    pub cursor: TilePos,
+    /// How far from the origin a target may be, if there's a limit
+    pub range: Option<u32>,
+}
+
+impl Targeting {
+    /// Whether the cursor is within range of the origin
+    pub fn in_range(&self) -> bool {
+        self.range
+            .is_none_or(|range| self.origin.distance(self.cursor) <= range)
+    }
+}
+
+/// Sent when the player confirms the tile under the targeting cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct TargetConfirmed(pub TilePos);
+
+pub(super) fn init_target_cursor(
+    mut commands: Commands,
+    player_qry: Query<&TilePos, With<Player>>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+) {
+    let Ok(&origin) = player_qry.get_single() else {
+        ui_state.set(GameUi::Main);
+        return;
+    };
+
+    commands.insert_resource(Targeting {
+        origin,
+        cursor: origin,
+        range: None,
+    });
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: CURSOR_COLOR,
+                custom_size: Some(Vec2::splat(TILE_SIZE_F32)),
+                ..Default::default()
+            },
+            transform: origin.as_transform(SpriteLayer::UI),
+            ..Default::default()
+        },
+        TargetCursor,
+    ));
+}
+
+pub(super) fn remove_target_cursor(mut commands: Commands) {
+    commands.remove_resource::<Targeting>();
+}
+
+/// Visible hostiles, nearest to the given position first
+fn visible_hostiles(
+    from: TilePos,
+    map: &Map,
+    mob_qry: &Query<&TilePos, With<Mob>>,
+    fov_qry: &Query<&FieldOfView>,
+) -> Vec<TilePos> {
+    mob_qry
+        .iter()
+        .copied()
+        .filter(|&tile| {
+            map.get(tile)
+                .and_then(|tile_entity| fov_qry.get(tile_entity).ok())
+                .is_some_and(|fov| *fov == FieldOfView::Visible)
+        })
+        .sorted_by_key(|&tile| (from.distance(tile), tile.x, tile.y))
+        .collect()
+}
+
+/// Start the cursor on the nearest visible hostile within range, if there is one
+pub(super) fn target_nearest_hostile(
+    mut targeting: ResMut<Targeting>,
+    map: Res<Map>,
+    mob_qry: Query<&TilePos, With<Mob>>,
+    fov_qry: Query<&FieldOfView>,
+) {
+    let range = targeting.range.unwrap_or(u32::MAX);
+    if let Some(&nearest) = visible_hostiles(targeting.origin, &map, &mob_qry, &fov_qry)
+        .first()
+        .filter(|&&nearest| targeting.origin.distance(nearest) <= range)
+    {
+        targeting.cursor = nearest;
+    }
+}
+
+pub(super) fn move_target_cursor(
+    actions: Res<Actions>,
+    mut cursor_evt: EventReader<CursorMoved>,
+    camera_qry: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
+    mut targeting: ResMut<Targeting>,
+    map: Res<Map>,
+) {
+    let mut delta = IVec2::ZERO;
+
+    if actions.perform(Action::WalkNorth)
+        || actions.perform(Action::WalkNortheast)
+        || actions.perform(Action::WalkNorthwest)
+    {
+        delta += IVec2::Y;
+    }
+    if actions.perform(Action::WalkEast)
+        || actions.perform(Action::WalkNortheast)
+        || actions.perform(Action::WalkSoutheast)
+    {
+        delta += IVec2::X;
+    }
+    if actions.perform(Action::WalkSouth)
+        || actions.perform(Action::WalkSoutheast)
+        || actions.perform(Action::WalkSouthwest)
+    {
+        delta += IVec2::NEG_Y;
+    }
+    if actions.perform(Action::WalkWest)
+        || actions.perform(Action::WalkSouthwest)
+        || actions.perform(Action::WalkNorthwest)
+    {
+        delta += IVec2::NEG_X;
+    }
+
+    if delta != IVec2::ZERO {
+        if let Some(cursor) = targeting
+            .cursor
+            .checked_add_signed(delta)
+            .filter(|&cursor| map.size.in_bounds(cursor))
+        {
+            targeting.cursor = cursor;
+        }
+    } else if let Some(cursor) = cursor_evt.iter().last() {
+        // The mouse can still be used to point at things
+        let (camera, camera_transform) = camera_qry.single();
+        if let Some(world_position) = camera.viewport_to_world_2d(camera_transform, cursor.position)
+        {
+            if let Some(tile) = TilePos::from_world(world_position)
+                .filter(|&tile| map.size.in_bounds(tile) && tile != targeting.cursor)
+            {
+                targeting.cursor = tile;
+            }
+        }
+    }
+}
+
+pub(super) fn cycle_targets(
+    actions: Res<Actions>,
+    mut targeting: ResMut<Targeting>,
+    map: Res<Map>,
+    mob_qry: Query<&TilePos, With<Mob>>,
+    fov_qry: Query<&FieldOfView>,
+) {
+    if !actions.perform(Action::CycleTarget) {
+        return;
+    }
+
+    let hostiles = visible_hostiles(targeting.origin, &map, &mob_qry, &fov_qry);
+    let next = match hostiles.iter().position(|&tile| tile == targeting.cursor) {
+        Some(idx) => hostiles.get(idx + 1).or(hostiles.first()),
+        None => hostiles.first(),
+    };
+
+    if let Some(&next) = next {
+        targeting.cursor = next;
+    }
+}
+
+pub(super) fn update_target_cursor(
+    targeting: Res<Targeting>,
+    mut cursor_qry: Query<(&mut Transform, &mut Sprite), With<TargetCursor>>,
+    mut looking_at_qry: Query<&mut Text, With<LookingAt>>,
+    describer: TileDescriber,
+    asset_server: Res<AssetServer>,
+) {
+    if !targeting.is_changed() {
+        return;
+    }
+
+    if let Ok((mut transform, mut sprite)) = cursor_qry.get_single_mut() {
+        *transform = targeting.cursor.as_transform(SpriteLayer::UI);
+        sprite.color = if targeting.in_range() {
+            CURSOR_COLOR
+        } else {
+            OUT_OF_RANGE_COLOR
+        };
+    }
+
+    if let Ok(mut text) = looking_at_qry.get_single_mut() {
+        set_looking_at(
+            &mut text,
+            describer.names_at(targeting.cursor),
+            asset_server.load("fonts/FiraMono-Medium.ttf"),
+        );
+    }
+}
+
+pub(super) fn confirm_target(
+    actions: Res<Actions>,
+    buttons: Res<Input<MouseButton>>,
+    targeting: Res<Targeting>,
+    mut confirm_evt: EventWriter<TargetConfirmed>,
+) {
+    if actions.perform(Action::Confirm) || buttons.just_pressed(MouseButton::Left) {
+        confirm_evt.send(TargetConfirmed(targeting.cursor));
+    }
+}
+
+/// Looking around has nothing to do with the target but return to the game
+pub(super) fn finish_looking(
+    mut confirm_evt: EventReader<TargetConfirmed>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+) {
+    if confirm_evt.iter().last().is_some() {
+        ui_state.set(GameUi::Main);
+    }
+}
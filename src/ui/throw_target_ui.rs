@@ -0,0 +1,177 @@
+//! Throwing an item from the inventory at a tile picked with the targeting cursor
+//!
+//! Most things simply land where they're thrown, but potions shatter, splashing their effect over
+//! everything around the tile.
+
+use std::collections::HashSet;
+
+use bevy::{ecs::query::Has, prelude::*};
+use itertools::Itertools;
+
+use crate::{
+    animation::{Animation, AnimationQueue},
+    combat::HP,
+    dungeon::{BlocksMovement, BlocksSight, Map, TilePos},
+    fieldofview::{compute_limited_fov, FieldOfView},
+    inventory::Inventory,
+    items::{Item, ItemId, ItemList},
+    magic::{CastSpell, Spell, SpellTarget},
+    setup::Player,
+    TurnState,
+};
+
+use super::{
+    spell_target_ui::TargetArea,
+    targeting::{TargetConfirmed, Targeting},
+    GameUi, Messages,
+};
+
+/// How far away something can be thrown
+const THROW_RANGE: u8 = 6;
+/// How far a shattered potion splashes from where it lands
+const SPLASH_RADIUS: u8 = 1;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub(super) struct ThrowTargetUi;
+
+/// The item from the player's inventory that's about to be thrown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub(super) struct ItemToThrow(pub Option<ItemId>);
+
+pub(super) fn init_throw_targeting(
+    mut commands: Commands,
+    item_to_throw: Res<ItemToThrow>,
+    item_list: Res<ItemList>,
+    targeting: Option<ResMut<Targeting>>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+) {
+    let (Some(item), Some(mut targeting)) = (item_to_throw.0, targeting) else {
+        ui_state.set(GameUi::Main);
+        return;
+    };
+    targeting.range = Some(u32::from(THROW_RANGE));
+
+    if let Item::Potion { .. } = item_list[item].data {
+        commands.spawn((
+            SpatialBundle::default(),
+            ThrowTargetUi,
+            TargetArea(i32::from(SPLASH_RADIUS)),
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn throw_item(
+    mut commands: Commands,
+    mut confirm_evt: EventReader<TargetConfirmed>,
+    targeting: Res<Targeting>,
+    item_to_throw: Res<ItemToThrow>,
+    item_list: Res<ItemList>,
+    asset_server: Res<AssetServer>,
+    map: Res<Map>,
+    tile_qry: Query<(&FieldOfView, Has<BlocksMovement>)>,
+    blocks_sight_qry: Query<&TilePos, With<BlocksSight>>,
+    targetable_qry: Query<(Entity, &TilePos), With<HP>>,
+    mut thrower_qry: Query<(Entity, &mut Inventory), With<Player>>,
+    mut animations: ResMut<AnimationQueue>,
+    mut ui_state: ResMut<NextState<GameUi>>,
+    mut next_state: ResMut<NextState<TurnState>>,
+    mut messages: ResMut<Messages>,
+) {
+    let Some(TargetConfirmed(target_tile)) = confirm_evt.iter().last().copied() else {
+        return;
+    };
+    let Some(item) = item_to_throw.0 else {
+        return;
+    };
+
+    match map
+        .get(target_tile)
+        .and_then(|tile_entity| tile_qry.get(tile_entity).ok())
+    {
+        Some((FieldOfView::Visible, false)) => {}
+        Some((FieldOfView::Visible, true)) => {
+            messages.add("There's no room to throw anything there.");
+            return;
+        }
+        _ => {
+            messages.add("You can't see that spot.");
+            return;
+        }
+    }
+
+    if !targeting.in_range() {
+        messages.add("That's too far away to throw.");
+        return;
+    }
+
+    let Ok((thrower, mut inventory)) = thrower_qry.get_single_mut() else {
+        return;
+    };
+    let Some(idx) = inventory.find(item) else {
+        ui_state.set(GameUi::Main);
+        return;
+    };
+    inventory.remove(idx);
+
+    let name = &item_list[item].name;
+    messages.add(format!("You throw the {name}"));
+    match item_list[item].data {
+        Item::Potion { effect } => {
+            let blockers: HashSet<_> = blocks_sight_qry.iter().copied().collect();
+            let area: HashSet<_> =
+                compute_limited_fov(target_tile, i32::from(SPLASH_RADIUS), |tile| {
+                    blockers.contains(&tile)
+                })
+                .into_iter()
+                .collect();
+            let targets = targetable_qry
+                .iter()
+                .filter(|&(_, target_pos)| area.contains(target_pos))
+                .map(|(target, _)| target)
+                .collect_vec();
+
+            // The splash works just like an area spell, going off wherever the potion lands
+            let splash = CastSpell {
+                caster: thrower,
+                spell: Spell {
+                    target: SpellTarget::Area(SPLASH_RADIUS),
+                    range: THROW_RANGE,
+                    effect,
+                },
+                item: None,
+            };
+            messages.add(format!("The {name} shatters!"));
+            animations.push(Animation::bolt(
+                targeting.origin,
+                target_tile,
+                effect.color(),
+            ));
+            animations.push(
+                Animation::burst(
+                    target_tile,
+                    area.into_iter().collect(),
+                    effect.color().with_a(0.5),
+                )
+                .then(targets.iter().map(|&target| splash.on(target))),
+            );
+
+            // Otherwise the turn ends once the splash has gone off
+            if targets.is_empty() {
+                next_state.set(TurnState::MonsterTurn);
+            }
+        }
+        _ => {
+            animations.push(Animation::bolt(targeting.origin, target_tile, Color::GRAY));
+            item_list.spawn(item, target_tile, &mut commands, &asset_server);
+            next_state.set(TurnState::MonsterTurn);
+        }
+    }
+
+    ui_state.set(GameUi::Main);
+}
+
+/// Forget about the item once targeting ends, whether it was thrown or not
+pub(super) fn clear_item_to_throw(mut item_to_throw: ResMut<ItemToThrow>) {
+    item_to_throw.0 = None;
+}
@@ -1,12 +1,19 @@
 use std::path::{Path, PathBuf};
 
-pub fn get_dat_path<P>(file: P) -> PathBuf
+/// Path to a file in the user's configuration directory, if they have one
+pub fn get_config_path<P>(file: P) -> Option<PathBuf>
 where
     P: AsRef<Path>,
 {
-    bevy::asset::FileAssetIo::get_base_path()
-        .join("assets/dat")
-        .join(file.as_ref())
+    dirs::config_dir().map(|dir| dir.join("swordsandrunes").join(file.as_ref()))
+}
+
+/// Path to a file in the user's data directory, if they have one
+pub fn get_data_path<P>(file: P) -> Option<PathBuf>
+where
+    P: AsRef<Path>,
+{
+    dirs::data_dir().map(|dir| dir.join("swordsandrunes").join(file.as_ref()))
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -16,6 +23,7 @@ pub enum SpriteLayer {
     Decoration,
     Item,
     Actor,
+    Effect,
     UI,
 }
 